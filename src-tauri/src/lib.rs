@@ -1,13 +1,21 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::path::Path;
-use tauri::Emitter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+use tauri::{Emitter, Manager, State};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GitBranch {
     name: String,
     is_current: bool,
     last_commit_date: i64,
+    is_remote: bool,
+    upstream: Option<String>, // Remote-tracking branch a local branch follows, e.g. "origin/main"
+    ahead: u32, // Commits on the local branch that its upstream lacks
+    behind: u32, // Commits on the upstream that the local branch lacks
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -17,6 +25,27 @@ pub struct GitRemote {
     is_push: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteDetails {
+    name: String,
+    url: String,
+    push_url: Option<String>, // Only set when pushes go to a different URL
+    fetch_refspecs: Vec<String>,
+    push_refspecs: Vec<String>,
+    tracking_branches: Vec<String>, // Local branches whose upstream is on this remote
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitTag {
+    name: String,
+    target_id: String, // Id of the commit (or other object) the tag points at
+    is_annotated: bool,
+    tagger: Option<String>,
+    date: String, // Tagger date, or the target commit date for lightweight tags
+    timestamp: i64,
+    message: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GitCommit {
     id: String,
@@ -26,6 +55,22 @@ pub struct GitCommit {
     short_id: String,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CommitFilter {
+    author: Option<String>, // Case-insensitive regex matched against "Name <email>"
+    committer: Option<String>,
+    since: Option<i64>, // Unix timestamps compared with the commit date, both inclusive
+    until: Option<i64>,
+    message: Option<String>, // Regex matched against the full commit message
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitPage {
+    commits: Vec<GitCommit>,
+    next_cursor: Option<String>, // Id of the last commit in this page, pass back as `after`
+    has_more: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileChange {
     path: String,
@@ -34,11 +79,137 @@ pub struct FileChange {
     deletions: u32,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphEdge {
+    from_lane: u32, // Lane in this row
+    to_lane: u32, // Lane in the next row
+    edge_type: String, // "pass" for lines crossing the row, "parent" and "merge" for lines to first and other parents
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphRow {
+    commit: GitCommit,
+    parents: Vec<String>,
+    lane: u32, // Lane the commit's node is drawn in
+    edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitGraphPage {
+    rows: Vec<GraphRow>,
+    lane_count: u32, // Widest row in this page
+    next_cursor: Option<String>,
+    has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileHistoryEntry {
+    commit: GitCommit,
+    path: String, // Path of the file in this commit
+    old_path: Option<String>, // Previous path when this commit renamed the file
+    status: String,
+    additions: u32,
+    deletions: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileHistoryPage {
+    entries: Vec<FileHistoryEntry>,
+    next_cursor: Option<String>,
+    next_path: Option<String>, // Path the file had before the last entry, pass back as after_path
+    has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitIdentity {
+    name: String,
+    email: String,
+    timestamp: i64,
+    timezone: String, // Offset the commit was made in, e.g. "+02:00"
+    date: String, // Local time in that timezone
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitTrailer {
+    key: String, // e.g. "Signed-off-by"
+    value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitDetails {
+    id: String,
+    short_id: String,
+    summary: String,
+    body: String, // Everything after the summary line
+    trailers: Vec<CommitTrailer>,
+    author: CommitIdentity,
+    committer: CommitIdentity,
+    parent_ids: Vec<String>,
+    tree_id: String,
+    refs: Vec<String>, // Short names of branches and tags pointing here, plus "HEAD"
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevisionComparison {
+    base_id: String,
+    head_id: String,
+    merge_base_id: Option<String>, // Commit the diff starts from in three-dot mode
+    changes: Vec<FileChange>,
+    summary: DiffSummary,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffSummary {
+    files_changed: u32,
+    insertions: u32,
+    deletions: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StagedChange {
     path: String,
     status: String, // "added", "modified", "deleted", "renamed"
     old_path: Option<String>, // For renamed files
+    additions: u32,
+    deletions: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkingTreeChange {
+    path: String,
+    status: String, // "modified", "deleted", "renamed", "untracked", "conflicted"
+    old_path: Option<String>, // For renamed files
+    additions: u32,
+    deletions: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineSelection {
+    old_line_number: Option<u32>, // Set for deleted lines
+    new_line_number: Option<u32>, // Set for added lines
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscardPlan {
+    files: Vec<WorkingTreeChange>,
+    token: String, // Pass to discard_changes to confirm exactly these changes
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CombinedDiffLine {
+    line_type: String, // "addition", "deletion" or "context"
+    markers: String, // One column per parent, '+' added or '-' removed relative to it
+    content: String,
+    new_line_number: Option<u32>,
+    hunk_index: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CombinedFileDiff {
+    path: String,
+    parent_ids: Vec<String>,
+    diff_lines: Vec<CombinedDiffLine>,
+    is_binary: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,6 +221,19 @@ pub struct GitStash {
     date: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StashChanges {
+    staged: Vec<FileChange>,
+    unstaged: Vec<FileChange>,
+    untracked: Vec<FileChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StashApplyResult {
+    conflicts: Vec<String>, // Paths left conflicted in the index
+    dropped: bool, // Whether the stash was removed afterwards
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileDiff {
     path: String,
@@ -66,6 +250,7 @@ pub struct DiffLine {
     content: String,
     old_line_number: Option<u32>,
     new_line_number: Option<u32>,
+    hunk_index: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -80,15 +265,20 @@ pub struct SearchResult {
     line_number: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlameInfo {
     commit_id: String,
     commit_short_id: String,
     author: String,
-    date: String,
+    date: String, // Author date
+    committer: String,
+    committer_date: String,
     line_number: u32,
     content: String,
     commit_message: String,
+    original_path: String,      // Path of the file in the commit the line came from
+    original_line_number: u32,  // Line number in that commit's version of the file
+    is_boundary: bool,          // Hunk reached the oldest commit considered, so may be older
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -97,6 +287,49 @@ pub struct FileBlame {
     blame_lines: Vec<BlameInfo>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlameStream {
+    stream_id: u64,
+    total_lines: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteProgress {
+    operation: String, // "fetch" or "push"
+    remote: String,
+    current_objects: u32, // Objects received when fetching, sent when pushing
+    total_objects: u32,
+    indexed_deltas: u32,
+    total_deltas: u32,
+    bytes: u64,
+    message: Option<String>, // Latest progress text sent by the server
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PullResult {
+    status: String, // "up-to-date" or "fast-forward"
+    commit_id: String, // Branch tip after the pull
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlameChunk {
+    stream_id: u64,
+    path: String,
+    start_line: u32, // 1-based line number of the first entry in blame_lines
+    total_lines: u32,
+    blame_lines: Vec<BlameInfo>,
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParentBlame {
+    skipped_commit_id: String, // Commit that last touched the line
+    revision: String,          // Its first parent, which the line number and path refer to
+    path: String,
+    blame: BlameInfo,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileTreeItem {
     name: String,
@@ -107,16 +340,170 @@ pub struct FileTreeItem {
     file_type: String,
 }
 
+/// Open repository handles shared by all commands through managed state,
+/// keyed by repository path. Opening a large repository and loading its
+/// object database is expensive, so a handle is reused until the refs or the
+/// index change on disk.
+#[derive(Default)]
+pub struct RepoSessions {
+    sessions: Mutex<HashMap<PathBuf, RepoSession>>,
+}
+
+struct RepoSession {
+    handle: RepoHandle,
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    fingerprint: RepoFingerprint,
+    walks: Vec<(String, git2::Oid, HistoryWalk)>, // Paused walks keyed by walk and cursor, oldest first
+}
+
+/// Paused history walks kept per repository. Each one holds a thread, so only
+/// the most recent ones are kept.
+const MAX_PAUSED_WALKS: usize = 8;
+
+#[derive(Clone)]
+pub struct RepoHandle(Arc<Mutex<git2::Repository>>);
+
+impl RepoHandle {
+    /// Locks the repository for the duration of a command. A panic in another
+    /// command doesn't leave the repository in a bad state, so poisoning is ignored.
+    pub fn lock(&self) -> MutexGuard<'_, git2::Repository> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Modification times of HEAD, the index, packed-refs and every directory
+/// under refs/. Git updates refs and the index by renaming lock files, so any
+/// change to them shows up here.
+#[derive(Debug, PartialEq, Eq)]
+struct RepoFingerprint(Vec<Option<SystemTime>>);
+
+impl RepoFingerprint {
+    fn read(git_dir: &Path, common_dir: &Path) -> Self {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        
+        let mut times = vec![
+            modified(&git_dir.join("HEAD")),
+            modified(&git_dir.join("index")),
+            modified(&common_dir.join("packed-refs")),
+        ];
+        
+        let mut dirs = vec![common_dir.join("refs")];
+        while let Some(dir) = dirs.pop() {
+            times.push(modified(&dir));
+            if let Ok(entries) = std::fs::read_dir(&dir) {
+                let mut subdirs: Vec<PathBuf> = entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+                    .map(|entry| entry.path())
+                    .collect();
+                subdirs.sort();
+                dirs.extend(subdirs);
+            }
+        }
+        
+        RepoFingerprint(times)
+    }
+}
+
+impl RepoSessions {
+    /// Returns the cached handle for `path`, reopening the repository if its
+    /// refs or index changed since it was opened.
+    pub fn open(&self, path: &str) -> Result<RepoHandle, git2::Error> {
+        let key = Path::new(path).canonicalize().unwrap_or_else(|_| PathBuf::from(path));
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        
+        if let Some(session) = sessions.get(&key) {
+            if RepoFingerprint::read(&session.git_dir, &session.common_dir) == session.fingerprint {
+                return Ok(session.handle.clone());
+            }
+        }
+        
+        let repo = match git2::Repository::open(&key) {
+            Ok(repo) => repo,
+            Err(e) => {
+                sessions.remove(&key);
+                return Err(e);
+            }
+        };
+        let git_dir = repo.path().to_path_buf();
+        let common_dir = repo.commondir().to_path_buf();
+        let fingerprint = RepoFingerprint::read(&git_dir, &common_dir);
+        let handle = RepoHandle(Arc::new(Mutex::new(repo)));
+        
+        sessions.insert(key, RepoSession {
+            handle: handle.clone(),
+            git_dir,
+            common_dir,
+            fingerprint,
+            walks: Vec::new(),
+        });
+        
+        Ok(handle)
+    }
+    
+    /// Drops the cached handle for `path`, if any.
+    pub fn close(&self, path: &str) {
+        let key = Path::new(path).canonicalize().unwrap_or_else(|_| PathBuf::from(path));
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+    }
+    
+    /// Takes the walk `walk_key` that stopped after `cursor`. Walks are dropped
+    /// with the session, so a walk is only returned while the refs are unchanged.
+    fn take_walk(&self, path: &str, walk_key: &str, cursor: git2::Oid) -> Option<HistoryWalk> {
+        let key = Path::new(path).canonicalize().unwrap_or_else(|_| PathBuf::from(path));
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let walks = &mut sessions.get_mut(&key)?.walks;
+        
+        let position = walks.iter().position(|(key, walk_cursor, _)| key == walk_key && *walk_cursor == cursor)?;
+        Some(walks.remove(position).2)
+    }
+    
+    /// Keeps `walk`, which stopped after `cursor`, for the next page.
+    fn keep_walk(&self, path: &str, walk_key: String, cursor: git2::Oid, walk: HistoryWalk) {
+        let key = Path::new(path).canonicalize().unwrap_or_else(|_| PathBuf::from(path));
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(session) = sessions.get_mut(&key) {
+            if session.walks.len() >= MAX_PAUSED_WALKS {
+                session.walks.remove(0);
+            }
+            session.walks.push((walk_key, cursor, walk));
+        }
+    }
+}
+
+#[tauri::command]
+fn close_repository(sessions: State<'_, RepoSessions>, path: String) {
+    sessions.close(&path);
+}
+
 #[tauri::command]
 fn get_git_branches() -> Result<Vec<GitBranch>, String> {
     let current_dir = env::current_dir().map_err(|e| e.to_string())?;
     let repo = git2::Repository::discover(current_dir).map_err(|e| e.to_string())?;
     
+    collect_branches(&repo)
+}
+
+#[tauri::command]
+fn get_git_branches_from_path(sessions: State<'_, RepoSessions>, path: String) -> Result<Vec<GitBranch>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    collect_branches(&repo)
+}
+
+fn collect_branches(repo: &git2::Repository) -> Result<Vec<GitBranch>, String> {
     let mut branches = Vec::new();
-    let branch_iter = repo.branches(Some(git2::BranchType::Local)).map_err(|e| e.to_string())?;
+    let branch_iter = repo.branches(None).map_err(|e| e.to_string())?;
     
     for branch_result in branch_iter {
-        let (branch, _) = branch_result.map_err(|e| e.to_string())?;
+        let (branch, branch_type) = branch_result.map_err(|e| e.to_string())?;
+        // Skip symbolic remote refs such as origin/HEAD
+        if branch.get().kind() == Some(git2::ReferenceType::Symbolic) {
+            continue;
+        }
+        
         if let Some(name) = branch.name().map_err(|e| e.to_string())? {
             // Get the last commit date for this branch
             let last_commit_date = match branch.get().peel_to_commit() {
@@ -124,55 +511,196 @@ fn get_git_branches() -> Result<Vec<GitBranch>, String> {
                 Err(_) => 0, // Default to epoch if we can't get the commit
             };
             
+            let upstream = match branch_type {
+                git2::BranchType::Local => branch.upstream().ok(),
+                git2::BranchType::Remote => None,
+            };
+            let upstream_name = match &upstream {
+                Some(upstream) => upstream.name().map_err(|e| e.to_string())?.map(|n| n.to_string()),
+                None => None,
+            };
+            
+            let (ahead, behind) = match (branch.get().target(), upstream.as_ref().and_then(|u| u.get().target())) {
+                (Some(local), Some(remote)) => repo.graph_ahead_behind(local, remote).map_err(|e| e.to_string())?,
+                _ => (0, 0),
+            };
+            
             branches.push(GitBranch {
                 name: name.to_string(),
                 is_current: branch.is_head(),
                 last_commit_date,
+                is_remote: branch_type == git2::BranchType::Remote,
+                upstream: upstream_name,
+                ahead: ahead as u32,
+                behind: behind as u32,
             });
         }
     }
     
-    // Sort branches by last commit date (descending - newest first)
-    branches.sort_by(|a, b| b.last_commit_date.cmp(&a.last_commit_date));
+    // Local branches first, each group sorted by last commit date (newest first)
+    branches.sort_by_key(|branch| (branch.is_remote, std::cmp::Reverse(branch.last_commit_date)));
     
     Ok(branches)
 }
 
 #[tauri::command]
-fn get_git_branches_from_path(path: String) -> Result<Vec<GitBranch>, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+fn create_branch(sessions: State<'_, RepoSessions>, path: String, branch_name: String, commit_id: String, checkout: Option<bool>) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    let mut branches = Vec::new();
-    let branch_iter = repo.branches(Some(git2::BranchType::Local)).map_err(|e| e.to_string())?;
+    if !git2::Branch::name_is_valid(&branch_name).map_err(|e| e.to_string())? {
+        return Err(format!("'{}' is not a valid branch name", branch_name));
+    }
     
-    for branch_result in branch_iter {
-        let (branch, _) = branch_result.map_err(|e| e.to_string())?;
-        if let Some(name) = branch.name().map_err(|e| e.to_string())? {
-            // Get the last commit date for this branch
-            let last_commit_date = match branch.get().peel_to_commit() {
-                Ok(commit) => commit.time().seconds(),
-                Err(_) => 0, // Default to epoch if we can't get the commit
-            };
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    
+    repo.branch(&branch_name, &commit, false).map_err(|e| match e.code() {
+        git2::ErrorCode::Exists => format!("A branch named '{}' already exists", branch_name),
+        _ => format!("Failed to create branch: {}", e),
+    })?;
+    
+    if checkout.unwrap_or(false) {
+        checkout_commit_tree(&repo, &commit, Some(&format!("refs/heads/{}", branch_name)))
+            .inspect_err(|_| remove_new_branch(&repo, &branch_name))?;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn rename_branch(sessions: State<'_, RepoSessions>, path: String, old_name: String, new_name: String, force: Option<bool>) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    if !git2::Branch::name_is_valid(&new_name).map_err(|e| e.to_string())? {
+        return Err(format!("'{}' is not a valid branch name", new_name));
+    }
+    
+    let mut branch = repo.find_branch(&old_name, git2::BranchType::Local)
+        .map_err(|_| format!("Branch '{}' not found", old_name))?;
+    
+    branch.rename(&new_name, force.unwrap_or(false)).map_err(|e| match e.code() {
+        git2::ErrorCode::Exists => format!("A branch named '{}' already exists", new_name),
+        _ => format!("Failed to rename branch: {}", e),
+    })?;
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn delete_branch(sessions: State<'_, RepoSessions>, path: String, branch_name: String, force: Option<bool>) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let mut branch = repo.find_branch(&branch_name, git2::BranchType::Local)
+        .map_err(|_| format!("Branch '{}' not found", branch_name))?;
+    
+    if branch.is_head() {
+        return Err(format!("Cannot delete branch '{}' because it is checked out", branch_name));
+    }
+    
+    // Like git branch -d, a branch is merged once its upstream, or HEAD when
+    // it has none, contains its tip
+    if !force.unwrap_or(false) {
+        let tip = branch.get().target().ok_or("Branch has no target commit")?;
+        let merge_target = match branch.upstream() {
+            Ok(upstream) => upstream.get().target(),
+            Err(_) => repo.head().ok().and_then(|head| head.target()),
+        };
+        
+        let is_merged = match merge_target {
+            Some(target) => target == tip || repo.graph_descendant_of(target, tip).map_err(|e| e.to_string())?,
+            None => false,
+        };
+        if !is_merged {
+            return Err(format!("Branch '{}' is not fully merged, delete it with force to discard its commits", branch_name));
+        }
+    }
+    
+    branch.delete().map_err(|e| format!("Failed to delete branch: {}", e))
+}
+
+#[tauri::command]
+fn checkout_branch(sessions: State<'_, RepoSessions>, path: String, branch_name: String) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let mut created_branch = None;
+    let branch = match repo.find_branch(&branch_name, git2::BranchType::Local) {
+        Ok(branch) => branch,
+        Err(_) => {
+            // Checking out a remote branch creates a local branch tracking it
+            let remote_branch = repo.find_branch(&branch_name, git2::BranchType::Remote)
+                .map_err(|_| format!("Branch '{}' not found", branch_name))?;
+            let local_name = branch_name.split_once('/').map(|(_, name)| name).unwrap_or(&branch_name);
+            if repo.find_branch(local_name, git2::BranchType::Local).is_ok() {
+                return Err(format!("A local branch named '{}' already exists", local_name));
+            }
             
-            branches.push(GitBranch {
-                name: name.to_string(),
-                is_current: branch.is_head(),
-                last_commit_date,
-            });
+            let commit = remote_branch.get().peel_to_commit().map_err(|e| e.to_string())?;
+            let mut local = repo.branch(local_name, &commit, false).map_err(|e| format!("Failed to create branch: {}", e))?;
+            created_branch = Some(local_name.to_string());
+            local.set_upstream(Some(&branch_name))
+                .inspect_err(|_| remove_new_branch(&repo, local_name))
+                .map_err(|e| e.to_string())?;
+            local
+        }
+    };
+    
+    let reference_name = branch.get().name().ok_or("Branch name is not valid UTF-8")?.to_string();
+    let commit = branch.get().peel_to_commit().map_err(|e| e.to_string())?;
+    
+    checkout_commit_tree(&repo, &commit, Some(&reference_name)).inspect_err(|_| {
+        if let Some(local_name) = &created_branch {
+            remove_new_branch(&repo, local_name);
         }
+    })
+}
+
+#[tauri::command]
+fn checkout_commit(sessions: State<'_, RepoSessions>, path: String, commit_id: String) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    
+    checkout_commit_tree(&repo, &commit, None)
+}
+
+// Deletes a branch this command just created after a later step failed
+fn remove_new_branch(repo: &git2::Repository, branch_name: &str) {
+    if let Ok(mut branch) = repo.find_branch(branch_name, git2::BranchType::Local) {
+        let _ = branch.delete();
+    }
+}
+
+// Checks out a commit and points HEAD at the given branch ref, or detaches
+// HEAD when there is none. Local changes are kept unless the checkout would
+// overwrite them.
+fn checkout_commit_tree(repo: &git2::Repository, commit: &git2::Commit, reference_name: Option<&str>) -> Result<(), String> {
+    if repo.state() != git2::RepositoryState::Clean {
+        return Err("Cannot check out while a merge, rebase or other operation is in progress".to_string());
     }
     
-    // Sort branches by last commit date (descending - newest first)
-    branches.sort_by(|a, b| b.last_commit_date.cmp(&a.last_commit_date));
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.safe();
+    repo.checkout_tree(commit.as_object(), Some(&mut checkout)).map_err(|e| match e.code() {
+        git2::ErrorCode::Conflict => "Your local changes would be overwritten by checkout, commit or stash them first".to_string(),
+        _ => format!("Failed to check out: {}", e),
+    })?;
     
-    Ok(branches)
+    match reference_name {
+        Some(reference_name) => repo.set_head(reference_name),
+        None => repo.set_head_detached(commit.id()),
+    }.map_err(|e| format!("Failed to update HEAD: {}", e))
 }
 
 #[tauri::command]
-fn get_git_remotes_from_path(path: String) -> Result<Vec<GitRemote>, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+fn get_git_remotes_from_path(sessions: State<'_, RepoSessions>, path: String) -> Result<Vec<GitRemote>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
     let mut remotes = Vec::new();
     let remote_names = repo.remotes().map_err(|e| e.to_string())?;
@@ -207,1394 +735,5526 @@ fn get_git_remotes_from_path(path: String) -> Result<Vec<GitRemote>, String> {
 }
 
 #[tauri::command]
-fn get_commits_from_path(path: String, branch_name: String) -> Result<Vec<GitCommit>, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+fn get_remote_details(sessions: State<'_, RepoSessions>, path: String, remote_name: String) -> Result<RemoteDetails, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    // Find the branch, with fallback to HEAD if branch not found
-    let branch = match repo.find_branch(&branch_name, git2::BranchType::Local) {
-        Ok(branch) => branch,
-        Err(_) => {
-            // If the specific branch doesn't exist, try to get the HEAD branch
-            let head = repo.head().map_err(|e| format!("Cannot find branch '{}' and HEAD reference not found: {}", branch_name, e))?;
-            if head.is_branch() {
-                repo.find_branch(
-                    head.shorthand().unwrap_or("HEAD"),
-                    git2::BranchType::Local
-                ).map_err(|e| format!("Cannot find branch '{}': {}", branch_name, e))?
-            } else {
-                return Err(format!("Cannot find branch '{}' and HEAD is not pointing to a branch", branch_name));
-            }
-        }
-    };
-    let commit = branch.get().peel_to_commit().map_err(|e| e.to_string())?;
+    remote_details(&repo, &remote_name)
+}
+
+#[tauri::command]
+fn add_remote(sessions: State<'_, RepoSessions>, path: String, remote_name: String, url: String) -> Result<RemoteDetails, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
-    revwalk.push(commit.id()).map_err(|e| e.to_string())?;
-    revwalk.set_sorting(git2::Sort::TIME).map_err(|e| e.to_string())?;
+    if !git2::Remote::is_valid_name(&remote_name) {
+        return Err(format!("'{}' is not a valid remote name", remote_name));
+    }
+    validate_remote_url(&repo, &url)?;
     
-    let mut commits = Vec::new();
-    let mut count = 0;
+    repo.remote(&remote_name, url.trim()).map_err(|e| match e.code() {
+        git2::ErrorCode::Exists => format!("Remote '{}' already exists", remote_name),
+        _ => format!("Failed to add remote: {}", e),
+    })?;
     
-    for oid in revwalk {
-        if count >= 50 { // Limit to first 50 commits
-            break;
-        }
-        
-        let oid = oid.map_err(|e| e.to_string())?;
-        let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
-        
-        let message = commit.message().unwrap_or("No message").to_string();
-        let author = commit.author();
-        let author_name = author.name().unwrap_or("Unknown").to_string();
-        let date = commit.time();
-        let date_str = format!("{}", chrono::DateTime::from_timestamp(date.seconds(), 0)
-            .unwrap_or_default()
-            .format("%Y-%m-%d %H:%M:%S"));
-        
-        commits.push(GitCommit {
-            id: oid.to_string(),
-            message: message.lines().next().unwrap_or(&message).to_string(),
-            author: author_name,
-            date: date_str,
-            short_id: oid.to_string()[0..8].to_string(),
-        });
-        
-        count += 1;
-    }
-    
-    Ok(commits)
+    remote_details(&repo, &remote_name)
 }
 
 #[tauri::command]
-fn get_commit_changes(path: String, commit_id: String) -> Result<Vec<FileChange>, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
-    
-    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+fn rename_remote(sessions: State<'_, RepoSessions>, path: String, old_name: String, new_name: String) -> Result<RemoteDetails, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    let tree = commit.tree().map_err(|e| e.to_string())?;
-    let parent_tree = if commit.parent_count() > 0 {
-        Some(commit.parent(0).map_err(|e| e.to_string())?.tree().map_err(|e| e.to_string())?)
-    } else {
-        None
-    };
+    if !git2::Remote::is_valid_name(&new_name) {
+        return Err(format!("'{}' is not a valid remote name", new_name));
+    }
+    repo.find_remote(&old_name).map_err(|_| format!("Remote '{}' not found", old_name))?;
     
-    let mut diff = repo.diff_tree_to_tree(
-        parent_tree.as_ref(),
-        Some(&tree),
-        None
-    ).map_err(|e| e.to_string())?;
+    // Renaming also moves the remote-tracking branches and the upstream
+    // configuration of branches tracking the remote
+    repo.remote_rename(&old_name, &new_name).map_err(|e| match e.code() {
+        git2::ErrorCode::Exists => format!("Remote '{}' already exists", new_name),
+        _ => format!("Failed to rename remote: {}", e),
+    })?;
     
-    diff.find_similar(None).map_err(|e| e.to_string())?;
+    remote_details(&repo, &new_name)
+}
+
+#[tauri::command]
+fn remove_remote(sessions: State<'_, RepoSessions>, path: String, remote_name: String) -> Result<Vec<String>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    let mut changes = Vec::new();
+    repo.find_remote(&remote_name).map_err(|_| format!("Remote '{}' not found", remote_name))?;
     
-    diff.foreach(
-        &mut |delta, _progress| {
-            let status = match delta.status() {
-                git2::Delta::Added => "added",
-                git2::Delta::Deleted => "deleted",
-                git2::Delta::Modified => "modified",
-                git2::Delta::Renamed => "renamed",
-                git2::Delta::Copied => "copied",
-                _ => "unknown",
-            };
-            
-            let path = delta.new_file().path()
-                .or_else(|| delta.old_file().path())
-                .and_then(|p| p.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-            
-            changes.push(FileChange {
-                path,
-                status: status.to_string(),
-                additions: 0, // Will be filled in the line callback
-                deletions: 0,
-            });
-            
-            true
-        },
-        None,
-        None,
-        None,
-    ).map_err(|e| e.to_string())?;
+    // These branches lose their upstream along with the remote
+    let tracking_branches = branches_tracking_remote(&repo, &remote_name)?;
+    repo.remote_delete(&remote_name).map_err(|e| format!("Failed to remove remote: {}", e))?;
     
-    Ok(changes)
+    Ok(tracking_branches)
 }
 
 #[tauri::command]
-fn get_file_diff(path: String, commit_id: String, file_path: String) -> Result<FileDiff, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| format!("Failed to open repository: {}", e))?;
+fn set_remote_url(sessions: State<'_, RepoSessions>, path: String, remote_name: String, url: String) -> Result<RemoteDetails, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    let oid = git2::Oid::from_str(&commit_id).map_err(|e| format!("Invalid commit ID: {}", e))?;
-    let commit = repo.find_commit(oid).map_err(|e| format!("Commit not found: {}", e))?;
+    repo.find_remote(&remote_name).map_err(|_| format!("Remote '{}' not found", remote_name))?;
+    validate_remote_url(&repo, &url)?;
+    repo.remote_set_url(&remote_name, url.trim()).map_err(|e| format!("Failed to set remote URL: {}", e))?;
     
-    let tree = commit.tree().map_err(|e| format!("Failed to get commit tree: {}", e))?;
-    let parent_tree = if commit.parent_count() > 0 {
-        Some(commit.parent(0).map_err(|e| format!("Failed to get parent: {}", e))?.tree().map_err(|e| format!("Failed to get parent tree: {}", e))?)
-    } else {
-        None
-    };
+    remote_details(&repo, &remote_name)
+}
+
+#[tauri::command]
+fn set_remote_push_url(sessions: State<'_, RepoSessions>, path: String, remote_name: String, push_url: Option<String>) -> Result<RemoteDetails, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    // Create diff options with limits to prevent large diffs from causing issues
-    let mut diff_opts = git2::DiffOptions::new();
-    diff_opts.context_lines(3);
-    diff_opts.max_size(1024 * 1024); // 1MB limit
+    repo.find_remote(&remote_name).map_err(|_| format!("Remote '{}' not found", remote_name))?;
     
-    let diff = repo.diff_tree_to_tree(
-        parent_tree.as_ref(),
-        Some(&tree),
-        Some(&mut diff_opts)
-    ).map_err(|e| format!("Failed to create diff: {}", e))?;
+    // An empty or missing push URL makes pushes use the fetch URL again
+    let push_url = push_url.filter(|url| !url.trim().is_empty());
+    if let Some(push_url) = &push_url {
+        validate_remote_url(&repo, push_url)?;
+    }
+    repo.remote_set_pushurl(&remote_name, push_url.as_deref().map(|url| url.trim()))
+        .map_err(|e| format!("Failed to set push URL: {}", e))?;
     
-    // Find the specific file in the diff
-    let mut file_found = false;
-    let mut file_status = "unknown";
-    let mut is_binary = false;
+    remote_details(&repo, &remote_name)
+}
+
+#[tauri::command]
+fn set_remote_refspecs(sessions: State<'_, RepoSessions>, path: String, remote_name: String, fetch_refspecs: Vec<String>, push_refspecs: Vec<String>) -> Result<RemoteDetails, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let remote = repo.find_remote(&remote_name).map_err(|_| format!("Remote '{}' not found", remote_name))?;
+    let old_fetch = string_array(remote.fetch_refspecs().map_err(|e| e.to_string())?);
+    let old_push = string_array(remote.push_refspecs().map_err(|e| e.to_string())?);
+    drop(remote);
+    
+    // libgit2 only validates refspecs when adding them, so put the old ones
+    // back if any of the new ones is rejected
+    if let Err(e) = replace_refspecs(&repo, &remote_name, &fetch_refspecs, &push_refspecs) {
+        replace_refspecs(&repo, &remote_name, &old_fetch, &old_push)?;
+        return Err(e);
+    }
     
-    // First pass: find if the file exists in this diff
-    for (_delta_idx, delta) in diff.deltas().enumerate() {
-        let delta_path = delta.new_file().path()
-            .or_else(|| delta.old_file().path())
-            .and_then(|p| p.to_str())
-            .unwrap_or("unknown");
-        
-        if delta_path == file_path {
-            file_found = true;
-            file_status = match delta.status() {
-                git2::Delta::Added => "added",
-                git2::Delta::Deleted => "deleted", 
-                git2::Delta::Modified => "modified",
-                git2::Delta::Renamed => "renamed",
-                git2::Delta::Copied => "copied",
-                _ => "unknown",
-            };
-            is_binary = delta.new_file().is_binary() || delta.old_file().is_binary();
-            break;
+    remote_details(&repo, &remote_name)
+}
+
+fn replace_refspecs(repo: &git2::Repository, remote_name: &str, fetch_refspecs: &[String], push_refspecs: &[String]) -> Result<(), String> {
+    let mut config = repo.config().map_err(|e| e.to_string())?;
+    for key in ["fetch", "push"] {
+        match config.remove_multivar(&format!("remote.{}.{}", remote_name, key), ".*") {
+            Ok(()) => {}
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+            Err(e) => return Err(format!("Failed to update refspecs: {}", e)),
         }
     }
     
-    if !file_found {
-        return Err(format!("File '{}' not found in commit changes", file_path));
+    for refspec in fetch_refspecs.iter().map(|spec| spec.trim()).filter(|spec| !spec.is_empty()) {
+        repo.remote_add_fetch(remote_name, refspec).map_err(|e| format!("Invalid fetch refspec '{}': {}", refspec, e.message()))?;
     }
-    
-    if is_binary {
-        return Ok(FileDiff {
-            path: file_path,
-            status: file_status.to_string(),
-            old_content: None,
-            new_content: None,
-            diff_lines: Vec::new(),
-            is_binary: true,
-        });
+    for refspec in push_refspecs.iter().map(|spec| spec.trim()).filter(|spec| !spec.is_empty()) {
+        repo.remote_add_push(remote_name, refspec).map_err(|e| format!("Invalid push refspec '{}': {}", refspec, e.message()))?;
     }
     
-    // Generate patch for text files
-    let mut patch_lines = Vec::new();
+    Ok(())
+}
+
+fn remote_details(repo: &git2::Repository, remote_name: &str) -> Result<RemoteDetails, String> {
+    let remote = repo.find_remote(remote_name).map_err(|_| format!("Remote '{}' not found", remote_name))?;
+    let url = remote.url().unwrap_or("").to_string();
+    
+    Ok(RemoteDetails {
+        name: remote_name.to_string(),
+        push_url: remote.pushurl().filter(|push_url| *push_url != url).map(|push_url| push_url.to_string()),
+        url,
+        fetch_refspecs: string_array(remote.fetch_refspecs().map_err(|e| e.to_string())?),
+        push_refspecs: string_array(remote.push_refspecs().map_err(|e| e.to_string())?),
+        tracking_branches: branches_tracking_remote(repo, remote_name)?,
+    })
+}
+
+fn branches_tracking_remote(repo: &git2::Repository, remote_name: &str) -> Result<Vec<String>, String> {
+    let mut tracking = Vec::new();
     
-    for (delta_idx, delta) in diff.deltas().enumerate() {
-        let delta_path = delta.new_file().path()
-            .or_else(|| delta.old_file().path())
-            .and_then(|p| p.to_str())
-            .unwrap_or("unknown");
+    for branch_result in repo.branches(Some(git2::BranchType::Local)).map_err(|e| e.to_string())? {
+        let (branch, _) = branch_result.map_err(|e| e.to_string())?;
+        let Some(reference_name) = branch.get().name() else {
+            continue;
+        };
         
-        if delta_path == file_path {
-            let patch = git2::Patch::from_diff(&diff, delta_idx).map_err(|e| format!("Failed to create patch: {}", e))?;
-            
-            if let Some(patch) = patch {
-                for hunk_idx in 0..patch.num_hunks() {
-                    let (_hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| format!("Failed to get hunk: {}", e))?;
-                    
-                    for line_idx in 0..hunk_lines {
-                        let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| format!("Failed to get line: {}", e))?;
-                        
-                        let line_content = String::from_utf8_lossy(line.content()).trim_end_matches('\n').to_string();
-                        let line_type = match line.origin() {
-                            '+' => "addition",
-                            '-' => "deletion", 
-                            ' ' => "context",
-                            _ => "context",
-                        };
-                        
-                        patch_lines.push(DiffLine {
-                            line_type: line_type.to_string(),
-                            content: line_content,
-                            old_line_number: line.old_lineno(),
-                            new_line_number: line.new_lineno(),
-                        });
-                    }
-                }
+        let upstream_remote = repo.branch_upstream_remote(reference_name).ok();
+        if upstream_remote.as_ref().and_then(|name| name.as_str()) == Some(remote_name) {
+            if let Some(name) = branch.name().map_err(|e| e.to_string())? {
+                tracking.push(name.to_string());
             }
-            break;
         }
     }
     
-    Ok(FileDiff {
-        path: file_path,
-        status: file_status.to_string(),
-        old_content: None,
-        new_content: None,
-        diff_lines: patch_lines,
-        is_binary: false,
-    })
+    tracking.sort();
+    Ok(tracking)
+}
+
+fn string_array(array: git2::string_array::StringArray) -> Vec<String> {
+    array.iter().flatten().map(|value| value.to_string()).collect()
+}
+
+// Accepts URLs with a scheme git understands, scp-like SSH addresses such as
+// git@host:owner/repo.git and paths to existing local repositories. Relative
+// paths are resolved against the working directory, or the git directory of
+// a bare repository.
+fn validate_remote_url(repo: &git2::Repository, url: &str) -> Result<(), String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err("Remote URL cannot be empty".to_string());
+    }
+    if url.chars().any(char::is_whitespace) {
+        return Err(format!("Remote URL '{}' must not contain spaces", url));
+    }
+    
+    if let Some((scheme, rest)) = url.split_once("://") {
+        return match scheme {
+            "https" | "http" | "ssh" | "git" | "file" | "git+ssh" | "ssh+git" if !rest.is_empty() => Ok(()),
+            "https" | "http" | "ssh" | "git" | "file" | "git+ssh" | "ssh+git" => Err(format!("Remote URL '{}' has no host or path", url)),
+            _ => Err(format!("Unsupported URL scheme '{}'", scheme)),
+        };
+    }
+    
+    if let Some((host, path)) = url.split_once(':') {
+        if !host.is_empty() && !host.contains('/') && !path.is_empty() {
+            return Ok(());
+        }
+    }
+    
+    let base = repo.workdir().unwrap_or_else(|| repo.path());
+    if base.join(url).exists() {
+        Ok(())
+    } else {
+        Err(format!("'{}' is neither a valid remote URL nor an existing local path", url))
+    }
 }
 
+const DEFAULT_COMMIT_PAGE_SIZE: usize = 50;
+const MAX_COMMIT_PAGE_SIZE: usize = 1000;
+
 #[tauri::command]
-fn open_repo_dialog(app: tauri::AppHandle) {
-    let _ = app.emit("menu-open-repo", ());
+fn get_tags(sessions: State<'_, RepoSessions>, path: String, sort_by: Option<String>) -> Result<Vec<GitTag>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let mut tags = Vec::new();
+    let tag_names = repo.tag_names(None).map_err(|e| e.to_string())?;
+    
+    for name in tag_names.iter().flatten() {
+        let reference = repo.find_reference(&format!("refs/tags/{}", name)).map_err(|e| e.to_string())?;
+        tags.push(to_git_tag(&repo, name, &reference)?);
+    }
+    
+    // Both orders put the newest release first
+    match sort_by.as_deref().unwrap_or("version") {
+        "version" => tags.sort_by(|a, b| compare_versions(&b.name, &a.name)),
+        "date" => tags.sort_by_key(|tag| std::cmp::Reverse(tag.timestamp)),
+        other => return Err(format!("Unknown tag sort order '{}', expected version or date", other)),
+    }
+    
+    Ok(tags)
 }
 
 #[tauri::command]
-fn global_search(path: String, query: String, branch_name: Option<String>, max_commits: Option<u32>) -> Result<Vec<SearchResult>, String> {
-    if query.trim().is_empty() {
-        return Ok(Vec::new());
+fn get_tags_containing_commit(sessions: State<'_, RepoSessions>, path: String, commit_id: String) -> Result<Vec<String>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    repo.find_commit(oid).map_err(|e| e.to_string())?;
+    
+    let tag_names = repo.tag_names(None).map_err(|e| e.to_string())?;
+    let mut tag_targets = Vec::new();
+    
+    for name in tag_names.iter().flatten() {
+        let reference = repo.find_reference(&format!("refs/tags/{}", name)).map_err(|e| e.to_string())?;
+        // Tags on trees or blobs can't contain a commit
+        if let Ok(target) = reference.peel_to_commit() {
+            tag_targets.push((name.to_string(), target.id()));
+        }
     }
     
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    // Walk everything the tags reach except the commit's own history, parents
+    // first, so a commit contains the target exactly when one of its parents does
+    let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE).map_err(|e| e.to_string())?;
+    for (_, target) in &tag_targets {
+        revwalk.push(*target).map_err(|e| e.to_string())?;
+    }
+    revwalk.hide(oid).map_err(|e| e.to_string())?;
+    
+    let mut descendants = HashSet::from([oid]);
+    for walked in revwalk {
+        let walked = walked.map_err(|e| e.to_string())?;
+        let commit = repo.find_commit(walked).map_err(|e| e.to_string())?;
+        if commit.parent_ids().any(|parent| descendants.contains(&parent)) {
+            descendants.insert(walked);
+        }
+    }
     
-    let query_lower = query.to_lowercase();
+    let mut containing: Vec<String> = tag_targets.into_iter()
+        .filter(|(_, target)| descendants.contains(target))
+        .map(|(name, _)| name)
+        .collect();
     
-    // Determine which branch to search (default to current branch if not specified)
-    let target_branch = if let Some(branch) = branch_name {
-        branch
-    } else {
-        // Get current branch
-        let head = repo.head().map_err(|e| format!("Failed to get HEAD: {}", e))?;
-        let branch_name = head.shorthand().unwrap_or("HEAD").to_string();
-        branch_name
-    };
+    // Oldest version first, so the first entry is the release that introduced the commit
+    containing.sort_by(|a, b| compare_versions(a, b));
     
-    // Find the branch and get commits
-    let branch_result = repo.find_branch(&target_branch, git2::BranchType::Local);
-    let branch = match branch_result {
-        Ok(b) => b,
-        Err(_) => {
-            // If branch not found, try to resolve HEAD
-            let head = repo.head().map_err(|e| format!("Failed to get HEAD: {}", e))?;
-            let commit = head.peel_to_commit().map_err(|e| format!("Failed to get commit: {}", e))?;
-            
-            // Search in recent commits from HEAD
-            let mut revwalk = repo.revwalk().map_err(|e| format!("Failed to create revwalk: {}", e))?;
-            revwalk.push(commit.id()).map_err(|e| format!("Failed to push commit: {}", e))?;
-            revwalk.set_sorting(git2::Sort::TIME).map_err(|e| format!("Failed to set sorting: {}", e))?;
-            
-            return search_commits_and_content(&repo, revwalk, &query_lower, max_commits);
+    Ok(containing)
+}
+
+fn to_git_tag(repo: &git2::Repository, name: &str, reference: &git2::Reference) -> Result<GitTag, String> {
+    let target = reference.peel(git2::ObjectType::Any).map_err(|e| e.to_string())?;
+    let annotation = reference.target().and_then(|oid| repo.find_tag(oid).ok());
+    
+    let (tagger, timestamp, message) = match &annotation {
+        Some(tag) => {
+            let tagger = tag.tagger();
+            let timestamp = tagger.as_ref().map(|t| t.when().seconds()).unwrap_or(0);
+            let tagger = tagger.map(|t| format!("{} <{}>",
+                t.name().unwrap_or("Unknown"),
+                t.email().unwrap_or("unknown@email.com")));
+            // Signed tags carry the signature at the end of the message
+            let message = tag.message().map(|m| {
+                let body = m.find("-----BEGIN PGP SIGNATURE-----").map_or(m, |start| &m[..start]);
+                body.trim_end().to_string()
+            });
+            (tagger, timestamp, message)
+        }
+        None => {
+            let timestamp = target.as_commit().map(|commit| commit.time().seconds()).unwrap_or(0);
+            (None, timestamp, None)
         }
     };
     
-    let commit = branch.get().peel_to_commit().map_err(|e| format!("Failed to get commit: {}", e))?;
-    let mut revwalk = repo.revwalk().map_err(|e| format!("Failed to create revwalk: {}", e))?;
-    revwalk.push(commit.id()).map_err(|e| format!("Failed to push commit: {}", e))?;
-    revwalk.set_sorting(git2::Sort::TIME).map_err(|e| format!("Failed to set sorting: {}", e))?;
-    
-    search_commits_and_content(&repo, revwalk, &query_lower, max_commits)
+    let date = chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    
+    Ok(GitTag {
+        name: name.to_string(),
+        target_id: target.id().to_string(),
+        is_annotated: annotation.is_some(),
+        tagger,
+        date,
+        timestamp,
+        message,
+    })
 }
 
-fn search_commits_and_content(repo: &git2::Repository, revwalk: git2::Revwalk, query: &str, max_commits: Option<u32>) -> Result<Vec<SearchResult>, String> {
-    let mut results = Vec::new();
-    let mut count = 0;
-    const MAX_RESULTS: usize = 50;
-    let max_commits_limit = max_commits.unwrap_or(100) as usize;
+// Natural ordering so that v1.10.0 sorts after v1.9.0
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let a_number = take_digits(&mut a_chars);
+                let b_number = take_digits(&mut b_chars);
+                let a_number = a_number.trim_start_matches('0');
+                let b_number = b_number.trim_start_matches('0');
+                let ordering = a_number.len().cmp(&b_number.len()).then_with(|| a_number.cmp(b_number));
+                if ordering != std::cmp::Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits
+}
+
+#[tauri::command]
+fn create_tag(sessions: State<'_, RepoSessions>, path: String, tag_name: String, commit_id: String, message: Option<String>, sign: Option<bool>, force: Option<bool>) -> Result<GitTag, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    for oid in revwalk {
-        if count >= max_commits_limit || results.len() >= MAX_RESULTS {
-            break;
+    let reference_name = format!("refs/tags/{}", tag_name);
+    if !git2::Reference::is_valid_name(&reference_name) {
+        return Err(format!("'{}' is not a valid tag name", tag_name));
+    }
+    let force = force.unwrap_or(false);
+    if !force && repo.find_reference(&reference_name).is_ok() {
+        return Err(format!("Tag '{}' already exists", tag_name));
+    }
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let target = repo.find_object(oid, None).map_err(|e| e.to_string())?;
+    
+    let message = message.filter(|m| !m.trim().is_empty());
+    let sign = sign.unwrap_or(false);
+    
+    match message {
+        None if sign => return Err("A signed tag needs a message".to_string()),
+        None => {
+            repo.tag_lightweight(&tag_name, &target, force).map_err(|e| format!("Failed to create tag: {}", e))?;
         }
-        
-        let oid = oid.map_err(|e| format!("Failed to get OID: {}", e))?;
-        let commit = repo.find_commit(oid).map_err(|e| format!("Failed to find commit: {}", e))?;
-        
-        // Skip merge commits (commits with more than 1 parent)
-        if commit.parent_count() > 1 {
-            count += 1;
-            continue;
-        }
-        
-        let message = commit.message().unwrap_or("No message").to_string();
-        let author = commit.author();
-        let author_name = author.name().unwrap_or("Unknown").to_string();
-        let date = commit.time();
-        let date_str = format!("{}", chrono::DateTime::from_timestamp(date.seconds(), 0)
-            .unwrap_or_default()
-            .format("%Y-%m-%d %H:%M:%S"));
-        
-        // Search in commit message
-        if message.to_lowercase().contains(query) {
-            results.push(SearchResult {
-                result_type: "commit".to_string(),
-                commit_id: oid.to_string(),
-                commit_message: message.clone(),
-                commit_author: author_name.clone(),
-                commit_date: date_str.clone(),
-                file_path: None,
-                content_preview: Some(message.lines().next().unwrap_or(&message).to_string()),
-                line_number: None,
-            });
-        }
-        
-        // Search in file names and content
-        if let Err(_) = search_commit_files_and_content(repo, &commit, query, &mut results, &author_name, &date_str) {
-            // Continue even if individual commit search fails
-        }
-        
-        count += 1;
-        
-        if results.len() >= MAX_RESULTS {
-            break;
+        Some(message) => {
+            let tagger = repo.signature().map_err(|e| {
+                format!("Cannot tag without an identity, set user.name and user.email in git config: {}", e)
+            })?;
+            let message = git2::message_prettify(message, Some(b'#')).map_err(|e| e.to_string())?;
+            
+            if sign {
+                create_signed_tag(&repo, &tag_name, &target, &tagger, &message, force)?;
+            } else {
+                repo.tag(&tag_name, &target, &tagger, &message, force).map_err(|e| format!("Failed to create tag: {}", e))?;
+            }
         }
     }
     
-    Ok(results)
+    let reference = repo.find_reference(&reference_name).map_err(|e| e.to_string())?;
+    to_git_tag(&repo, &tag_name, &reference)
 }
 
-fn search_commit_files_and_content(
-    repo: &git2::Repository,
-    commit: &git2::Commit,
-    query: &str,
-    results: &mut Vec<SearchResult>,
-    author_name: &str,
-    date_str: &str,
-) -> Result<(), String> {
-    let tree = commit.tree().map_err(|e| format!("Failed to get tree: {}", e))?;
-    let parent_tree = if commit.parent_count() > 0 {
-        Some(commit.parent(0).map_err(|e| format!("Failed to get parent: {}", e))?.tree().map_err(|e| format!("Failed to get parent tree: {}", e))?)
-    } else {
-        None
-    };
+#[tauri::command]
+fn delete_tag(sessions: State<'_, RepoSessions>, path: String, tag_name: String) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    let mut diff_opts = git2::DiffOptions::new();
-    diff_opts.context_lines(2);
-    diff_opts.max_size(512 * 1024); // 512KB limit for content search
+    repo.tag_delete(&tag_name).map_err(|e| match e.code() {
+        git2::ErrorCode::NotFound => format!("Tag '{}' not found", tag_name),
+        _ => format!("Failed to delete tag: {}", e),
+    })
+}
+
+#[tauri::command(async)]
+fn push_tag(path: String, remote_name: String, tag_name: String) -> Result<(), String> {
+    let repo = open_transfer_repository(&path)?;
     
-    let diff = repo.diff_tree_to_tree(
-        parent_tree.as_ref(),
-        Some(&tree),
-        Some(&mut diff_opts)
-    ).map_err(|e| format!("Failed to create diff: {}", e))?;
+    let reference_name = format!("refs/tags/{}", tag_name);
+    repo.find_reference(&reference_name).map_err(|_| format!("Tag '{}' not found", tag_name))?;
     
-    for (delta_idx, delta) in diff.deltas().enumerate() {
-        if results.len() >= 50 { // Limit results
-            break;
-        }
-        
-        let file_path = delta.new_file().path()
-            .or_else(|| delta.old_file().path())
-            .and_then(|p| p.to_str())
-            .unwrap_or("unknown");
-        
-        // Search in file names
-        if file_path.to_lowercase().contains(query) {
-            results.push(SearchResult {
-                result_type: "file".to_string(),
-                commit_id: commit.id().to_string(),
-                commit_message: commit.message().unwrap_or("No message").lines().next().unwrap_or("").to_string(),
-                commit_author: author_name.to_string(),
-                commit_date: date_str.to_string(),
-                file_path: Some(file_path.to_string()),
-                content_preview: Some(format!("File: {}", file_path)),
-                line_number: None,
-            });
-        }
-        
-        // Search in file content (only for text files)
-        if !delta.new_file().is_binary() && !delta.old_file().is_binary() {
-            if let Ok(Some(patch)) = git2::Patch::from_diff(&diff, delta_idx) {
-                for hunk_idx in 0..patch.num_hunks() {
-                    if let Ok((_, hunk_lines)) = patch.hunk(hunk_idx) {
-                        for line_idx in 0..hunk_lines {
-                            if let Ok(line) = patch.line_in_hunk(hunk_idx, line_idx) {
-                                let line_content = String::from_utf8_lossy(line.content());
-                                if line_content.to_lowercase().contains(query) {
-                                    let preview = line_content.trim_end_matches('\n').to_string();
-                                    let preview_truncated = if preview.len() > 100 {
-                                        format!("{}...", &preview[..97])
-                                    } else {
-                                        preview
-                                    };
-                                    
-                                    results.push(SearchResult {
-                                        result_type: "content".to_string(),
-                                        commit_id: commit.id().to_string(),
-                                        commit_message: commit.message().unwrap_or("No message").lines().next().unwrap_or("").to_string(),
-                                        commit_author: author_name.to_string(),
-                                        commit_date: date_str.to_string(),
-                                        file_path: Some(file_path.to_string()),
-                                        content_preview: Some(preview_truncated),
-                                        line_number: line.new_lineno().or(line.old_lineno()),
-                                    });
-                                    
-                                    // Limit content results per file
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
+    push_refspecs(&repo, &remote_name, &[format!("{0}:{0}", reference_name)], &mut |_| {})
+}
+
+#[tauri::command(async)]
+fn delete_remote_tag(path: String, remote_name: String, tag_name: String) -> Result<(), String> {
+    let repo = open_transfer_repository(&path)?;
     
-    Ok(())
+    // Pushing an empty source deletes the ref on the remote
+    push_refspecs(&repo, &remote_name, &[format!(":refs/tags/{}", tag_name)], &mut |_| {})
 }
 
-#[tauri::command]
-fn get_file_blame(path: String, commit_id: String, file_path: String) -> Result<FileBlame, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+/// Opens a repository handle of its own for a network operation. Commands
+/// that talk to a remote run off the main thread and can take minutes, so
+/// they must not hold the shared session handle other commands wait on.
+fn open_transfer_repository(path: &str) -> Result<git2::Repository, String> {
+    git2::Repository::open(path).map_err(|e| e.to_string())
+}
+
+// libgit2 can't sign tags, so build the tag object by hand like git tag -s does
+fn create_signed_tag(repo: &git2::Repository, tag_name: &str, target: &git2::Object, tagger: &git2::Signature, message: &str, force: bool) -> Result<git2::Oid, String> {
+    let mut buffer = format!(
+        "object {}\ntype {}\ntag {}\ntagger {}\n\n{}",
+        target.id(),
+        target.kind().map(|kind| kind.str()).unwrap_or("commit"),
+        tag_name,
+        format_signature(tagger),
+        message
+    );
+    buffer.push_str(&gpg_sign(repo, tagger, &buffer)?);
+    
+    let odb = repo.odb().map_err(|e| e.to_string())?;
+    let oid = odb.write(git2::ObjectType::Tag, buffer.as_bytes()).map_err(|e| format!("Failed to write tag: {}", e))?;
+    repo.reference(&format!("refs/tags/{}", tag_name), oid, force, &format!("tag: tagging {}", target.id()))
+        .map_err(|e| format!("Failed to create tag: {}", e))?;
+    
+    Ok(oid)
+}
+
+// Formats an identity the way it appears in raw commit and tag objects
+fn format_signature(signature: &git2::Signature) -> String {
+    let when = signature.when();
+    let offset = when.offset_minutes();
+    format!(
+        "{} <{}> {} {}{:02}{:02}",
+        signature.name().unwrap_or("Unknown"),
+        signature.email().unwrap_or("unknown@email.com"),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+fn gpg_sign(repo: &git2::Repository, signer: &git2::Signature, payload: &str) -> Result<String, String> {
+    use std::io::Write;
     
-    // Get the commit
-    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    let config = repo.config().map_err(|e| e.to_string())?;
+    let program = config.get_string("gpg.program").unwrap_or_else(|_| "gpg".to_string());
+    let key = config.get_string("user.signingkey").unwrap_or_else(|_| {
+        format!("{} <{}>", signer.name().unwrap_or(""), signer.email().unwrap_or(""))
+    });
+    
+    let mut child = std::process::Command::new(&program)
+        .args(["--status-fd=2", "-bsau", &key])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run gpg program '{}': {}", program, e))?;
+    
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.as_bytes()).map_err(|e| format!("Failed to send tag to gpg: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("Failed to run gpg program '{}': {}", program, e))?;
     
-    // Get the tree
-    let tree = commit.tree().map_err(|e| e.to_string())?;
+    let signature = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() || signature.is_empty() {
+        return Err(format!("gpg failed to sign the tag: {}", String::from_utf8_lossy(&output.stderr).trim_end()));
+    }
     
-    // Find the file in the tree
-    let tree_entry = tree.get_path(Path::new(&file_path)).map_err(|e| {
-        format!("File '{}' not found in commit '{}': {}", file_path, commit_id, e)
+    Ok(if signature.ends_with('\n') { signature } else { format!("{}\n", signature) })
+}
+
+#[tauri::command(async)]
+fn fetch_remote(app: tauri::AppHandle, path: String, remote_name: Option<String>, prune: Option<bool>) -> Result<(), String> {
+    let repo = open_transfer_repository(&path)?;
+    
+    // Without a remote name every configured remote is fetched
+    let remote_names = match remote_name {
+        Some(name) => vec![name],
+        None => repo.remotes().map_err(|e| e.to_string())?.iter().flatten().map(|name| name.to_string()).collect(),
+    };
+    
+    for remote_name in &remote_names {
+        fetch_from_remote(&repo, remote_name, prune.unwrap_or(false), &mut |progress| {
+            let _ = app.emit("remote-progress", progress);
+        })?;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command(async)]
+fn pull(app: tauri::AppHandle, path: String) -> Result<PullResult, String> {
+    let repo = open_transfer_repository(&path)?;
+    fetch_upstream(&repo, &mut |progress| {
+        let _ = app.emit("remote-progress", progress);
     })?;
+    drop(repo);
+    
+    // The worktree and index are written through the shared handle, so no
+    // other command works on them in the meantime
+    let sessions = app.state::<RepoSessions>();
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    fast_forward_to_upstream(&repo)
+}
+
+#[tauri::command(async)]
+fn push(app: tauri::AppHandle, path: String, remote_name: Option<String>, branch_name: Option<String>, force: Option<bool>, set_upstream: Option<bool>) -> Result<(), String> {
+    let repo = open_transfer_repository(&path)?;
     
-    // Get the blob
-    let blob = repo.find_blob(tree_entry.id()).map_err(|e| e.to_string())?;
+    push_branch(&repo, remote_name.as_deref(), branch_name.as_deref(), force.unwrap_or(false), set_upstream.unwrap_or(false), &mut |progress| {
+        let _ = app.emit("remote-progress", progress);
+    })
+}
+
+fn fetch_from_remote(repo: &git2::Repository, remote_name: &str, prune: bool, on_progress: &mut dyn FnMut(RemoteProgress)) -> Result<(), String> {
+    let mut remote = repo.find_remote(remote_name).map_err(|_| format!("Remote '{}' not found", remote_name))?;
+    
+    let reporter = std::cell::RefCell::new(ProgressReporter::new("fetch", remote_name, on_progress));
+    let mut callbacks = remote_callbacks(repo)?;
+    callbacks.transfer_progress(|stats| {
+        reporter.borrow_mut().transfer(stats.received_objects(), stats.total_objects(), stats.indexed_deltas(), stats.total_deltas(), stats.received_bytes());
+        true
+    });
+    callbacks.sideband_progress(|data| {
+        reporter.borrow_mut().message(data);
+        true
+    });
+    
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+    if prune {
+        fetch_options.prune(git2::FetchPrune::On);
+    }
     
-    // Check if file is binary
-    if blob.is_binary() {
-        return Err("Cannot show blame for binary files".to_string());
+    // An empty refspec list uses the remote's configured fetch refspecs
+    remote.fetch::<&str>(&[], Some(&mut fetch_options), None).map_err(|e| remote_error("fetch from", remote_name, e))
+}
+
+// Returns the reference name, short name and target of the checked out branch
+fn current_pull_branch(repo: &git2::Repository) -> Result<(String, String, git2::Oid), String> {
+    let head = repo.head().map_err(|e| e.to_string())?;
+    if !head.is_branch() {
+        return Err("Cannot pull with a detached HEAD, check out a branch first".to_string());
     }
+    let reference_name = head.name().ok_or("Branch name is not valid UTF-8")?.to_string();
+    let branch_name = head.shorthand().unwrap_or(&reference_name).to_string();
+    let local_oid = head.target().ok_or("Branch has no target commit")?;
     
-    // Check file size limits for performance
-    const MAX_BLAME_SIZE: usize = 1024 * 1024; // 1MB
-    const MAX_BLAME_LINES: usize = 3000; // 3000 lines
+    Ok((reference_name, branch_name, local_oid))
+}
+
+// Fetches the remote the current branch's upstream lives on
+fn fetch_upstream(repo: &git2::Repository, on_progress: &mut dyn FnMut(RemoteProgress)) -> Result<(), String> {
+    let (reference_name, branch_name, _) = current_pull_branch(repo)?;
+    
+    let remote_name = repo.branch_upstream_remote(&reference_name)
+        .map_err(|_| format!("Branch '{}' has no upstream branch", branch_name))?;
+    let remote_name = remote_name.as_str().ok_or("Remote name is not valid UTF-8")?;
     
-    if blob.size() > MAX_BLAME_SIZE {
-        return Err(format!("File too large for blame view ({}KB > 1MB)", blob.size() / 1024));
+    fetch_from_remote(repo, remote_name, false, on_progress)
+}
+
+// Fast-forwards the current branch to its already fetched upstream
+fn fast_forward_to_upstream(repo: &git2::Repository) -> Result<PullResult, String> {
+    let (reference_name, branch_name, local_oid) = current_pull_branch(repo)?;
+    
+    let upstream_name = repo.branch_upstream_name(&reference_name).map_err(|e| e.to_string())?;
+    let upstream_name = upstream_name.as_str().ok_or("Upstream name is not valid UTF-8")?;
+    let upstream_oid = repo.refname_to_id(upstream_name)
+        .map_err(|_| format!("Upstream branch '{}' no longer exists on the remote", upstream_name))?;
+    
+    if upstream_oid == local_oid || repo.graph_descendant_of(local_oid, upstream_oid).map_err(|e| e.to_string())? {
+        return Ok(PullResult {
+            status: "up-to-date".to_string(),
+            commit_id: local_oid.to_string(),
+        });
+    }
+    if !repo.graph_descendant_of(upstream_oid, local_oid).map_err(|e| e.to_string())? {
+        return Err(format!("Cannot fast-forward '{}', it has diverged from its upstream, merge or rebase first", branch_name));
     }
     
-    // Get file content to check line count
-    let content = String::from_utf8(blob.content().to_vec()).map_err(|e| format!("File is not valid UTF-8: {}", e))?;
-    let line_count = content.lines().count();
+    let upstream_commit = repo.find_commit(upstream_oid).map_err(|e| e.to_string())?;
+    checkout_commit_tree(repo, &upstream_commit, Some(&reference_name))?;
+    repo.find_reference(&reference_name)
+        .and_then(|mut reference| reference.set_target(upstream_oid, "pull: Fast-forward"))
+        .map_err(|e| format!("Failed to update branch: {}", e))?;
     
-    if line_count > MAX_BLAME_LINES {
-        return Err(format!("File has too many lines for blame view ({} > {})", line_count, MAX_BLAME_LINES));
-    }
+    Ok(PullResult {
+        status: "fast-forward".to_string(),
+        commit_id: upstream_oid.to_string(),
+    })
+}
+
+// Pushes a branch, the current one by default, to its upstream remote or origin
+fn push_branch(repo: &git2::Repository, remote_name: Option<&str>, branch_name: Option<&str>, force: bool, set_upstream: bool, on_progress: &mut dyn FnMut(RemoteProgress)) -> Result<(), String> {
+    let branch_name = match branch_name {
+        Some(name) => name.to_string(),
+        None => {
+            let head = repo.head().map_err(|e| e.to_string())?;
+            if !head.is_branch() {
+                return Err("Cannot push a detached HEAD, check out a branch first".to_string());
+            }
+            head.shorthand().ok_or("Branch name is not valid UTF-8")?.to_string()
+        }
+    };
+    let mut branch = repo.find_branch(&branch_name, git2::BranchType::Local)
+        .map_err(|_| format!("Branch '{}' not found", branch_name))?;
+    let reference_name = format!("refs/heads/{}", branch_name);
+    
+    let remote_name = match remote_name {
+        Some(name) => name.to_string(),
+        None => repo.branch_upstream_remote(&reference_name).ok()
+            .and_then(|name| name.as_str().map(|name| name.to_string()))
+            .unwrap_or_else(|| "origin".to_string()),
+    };
     
-    // Create blame options
-    let mut blame_options = git2::BlameOptions::new();
-    blame_options.track_copies_same_commit_moves(true);
-    blame_options.track_copies_same_commit_copies(true);
+    let refspec = format!("{}{1}:{1}", if force { "+" } else { "" }, reference_name);
+    push_refspecs(repo, &remote_name, &[refspec], on_progress)?;
     
-    // Get blame for the file
-    let blame = repo.blame_file(Path::new(&file_path), Some(&mut blame_options)).map_err(|e| e.to_string())?;
+    if set_upstream {
+        branch.set_upstream(Some(&format!("{}/{}", remote_name, branch_name)))
+            .map_err(|e| format!("Failed to set upstream: {}", e))?;
+    }
     
-    let mut blame_lines = Vec::new();
+    Ok(())
+}
+
+fn push_refspecs(repo: &git2::Repository, remote_name: &str, refspecs: &[String], on_progress: &mut dyn FnMut(RemoteProgress)) -> Result<(), String> {
+    let mut remote = repo.find_remote(remote_name).map_err(|_| format!("Remote '{}' not found", remote_name))?;
+    
+    // The server reports per-ref rejections here instead of failing the push
+    let rejection = std::cell::RefCell::new(None);
+    let reporter = std::cell::RefCell::new(ProgressReporter::new("push", remote_name, on_progress));
+    let mut callbacks = remote_callbacks(repo)?;
+    callbacks.push_update_reference(|refname, status| {
+        if let Some(status) = status {
+            *rejection.borrow_mut() = Some(format!("Remote rejected {}: {}", refname, status));
+        }
+        Ok(())
+    });
+    callbacks.push_transfer_progress(|current, total, bytes| {
+        reporter.borrow_mut().transfer(current, total, 0, 0, bytes);
+    });
+    callbacks.sideband_progress(|data| {
+        reporter.borrow_mut().message(data);
+        true
+    });
+    
+    let mut push_options = git2::PushOptions::new();
+    push_options.remote_callbacks(callbacks);
+    remote.push(refspecs, Some(&mut push_options)).map_err(|e| remote_error("push to", remote_name, e))?;
+    drop(push_options);
+    
+    match rejection.into_inner() {
+        Some(message) => Err(message),
+        None => Ok(()),
+    }
+}
+
+const REMOTE_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+// Transfer callbacks fire for every object, so progress is forwarded at most
+// every REMOTE_PROGRESS_INTERVAL, plus once the transfer completes
+struct ProgressReporter<'a> {
+    progress: RemoteProgress,
+    last_report: Option<std::time::Instant>,
+    on_progress: &'a mut dyn FnMut(RemoteProgress),
+}
+
+impl<'a> ProgressReporter<'a> {
+    fn new(operation: &str, remote: &str, on_progress: &'a mut dyn FnMut(RemoteProgress)) -> Self {
+        ProgressReporter {
+            progress: RemoteProgress {
+                operation: operation.to_string(),
+                remote: remote.to_string(),
+                current_objects: 0,
+                total_objects: 0,
+                indexed_deltas: 0,
+                total_deltas: 0,
+                bytes: 0,
+                message: None,
+            },
+            last_report: None,
+            on_progress,
+        }
+    }
     
-    for (line_num, line_content) in content.lines().enumerate() {
-        let line_number = (line_num + 1) as u32;
+    fn transfer(&mut self, current_objects: usize, total_objects: usize, indexed_deltas: usize, total_deltas: usize, bytes: usize) {
+        self.progress.current_objects = current_objects as u32;
+        self.progress.total_objects = total_objects as u32;
+        self.progress.indexed_deltas = indexed_deltas as u32;
+        self.progress.total_deltas = total_deltas as u32;
+        self.progress.bytes = bytes as u64;
         
-        // Get blame info for this line
-        if let Some(hunk) = blame.get_line(line_number as usize) {
-            let commit_oid = hunk.final_commit_id();
-            let blame_commit = repo.find_commit(commit_oid).map_err(|e| e.to_string())?;
-            
-            // Get author and date info
-            let author = blame_commit.author();
-            let author_name = author.name().unwrap_or("Unknown").to_string();
-            let commit_time = blame_commit.time();
-            
-            // Format date
-            let date = chrono::DateTime::from_timestamp(commit_time.seconds(), 0)
-                .map(|dt| dt.format("%Y-%m-%d").to_string())
-                .unwrap_or_else(|| "Unknown".to_string());
-            
-            // Get commit message (first line only)
-            let commit_message = blame_commit.message()
-                .unwrap_or("No message")
-                .lines()
-                .next()
-                .unwrap_or("")
-                .to_string();
-            
-            blame_lines.push(BlameInfo {
-                commit_id: commit_oid.to_string(),
-                commit_short_id: commit_oid.to_string()[..8].to_string(),
-                author: author_name,
-                date,
-                line_number,
-                content: line_content.to_string(),
-                commit_message,
-            });
-        } else {
-            // Fallback for lines without blame info
-            blame_lines.push(BlameInfo {
-                commit_id: commit_id.clone(),
-                commit_short_id: commit_id[..8].to_string(),
-                author: "Unknown".to_string(),
-                date: "Unknown".to_string(),
-                line_number,
-                content: line_content.to_string(),
-                commit_message: "Unknown".to_string(),
-            });
+        let finished = current_objects == total_objects && indexed_deltas == total_deltas;
+        let due = match self.last_report {
+            Some(last_report) => last_report.elapsed() >= REMOTE_PROGRESS_INTERVAL,
+            None => true,
+        };
+        if finished || due {
+            self.report();
         }
     }
     
-    Ok(FileBlame {
-        path: file_path,
-        blame_lines,
-    })
+    fn message(&mut self, data: &[u8]) {
+        let text = String::from_utf8_lossy(data).trim().to_string();
+        if !text.is_empty() {
+            self.progress.message = Some(text);
+            self.report();
+        }
+    }
+    
+    fn report(&mut self) {
+        self.last_report = Some(std::time::Instant::now());
+        (self.on_progress)(self.progress.clone());
+    }
 }
 
-#[tauri::command]
-fn get_commit_file_tree(path: String, commit_id: String) -> Result<Vec<FileTreeItem>, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+// Credentials come from the SSH agent for SSH remotes and the configured
+// credential helper for HTTPS ones
+fn remote_callbacks<'a>(repo: &git2::Repository) -> Result<git2::RemoteCallbacks<'a>, String> {
+    let config = repo.config().map_err(|e| e.to_string())?;
+    let mut attempts = 0;
+    
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        // libgit2 keeps asking as long as we keep answering, so give up
+        // once the same credentials have been rejected
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::from_str("no valid credentials found"));
+        }
+        
+        if allowed.contains(git2::CredentialType::USERNAME) {
+            return git2::Cred::username(username_from_url.unwrap_or("git"));
+        }
+        if allowed.contains(git2::CredentialType::SSH_KEY) {
+            return git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"));
+        }
+        if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            return git2::Cred::credential_helper(&config, url, username_from_url);
+        }
+        if allowed.contains(git2::CredentialType::DEFAULT) {
+            return git2::Cred::default();
+        }
+        Err(git2::Error::from_str("no supported authentication method"))
+    });
     
-    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
-    let tree = commit.tree().map_err(|e| e.to_string())?;
+    Ok(callbacks)
+}
+
+fn remote_error(action: &str, remote_name: &str, e: git2::Error) -> String {
+    match (e.code(), e.class()) {
+        (git2::ErrorCode::Auth, _) => format!("Authentication failed for remote '{}': {}", remote_name, e.message()),
+        (git2::ErrorCode::NotFastForward, _) => {
+            format!("Updates were rejected because the remote '{}' contains work you do not have locally", remote_name)
+        }
+        (_, git2::ErrorClass::Net | git2::ErrorClass::Http | git2::ErrorClass::Ssh | git2::ErrorClass::Ssl) => {
+            format!("Could not reach remote '{}': {}", remote_name, e.message())
+        }
+        _ => format!("Failed to {} '{}': {}", action, remote_name, e.message()),
+    }
+}
+
+#[tauri::command]
+fn get_commits_from_path(sessions: State<'_, RepoSessions>, path: String, branch_name: String) -> Result<Vec<GitCommit>, String> {
+    let page = get_commits_page(sessions, path, branch_name, None, None)?;
+    Ok(page.commits)
+}
+
+#[tauri::command]
+fn get_commits_page(sessions: State<'_, RepoSessions>, path: String, branch_name: String, after: Option<String>, page_size: Option<u32>) -> Result<CommitPage, String> {
+    get_commits_for_revspecs(sessions, path, vec![branch_name], after, page_size, None)
+}
+
+/// Lists commits reachable from any of `revspecs`, which take the forms
+/// `git log` accepts: refs or commit ids, `^rev` exclusions, `A..B`, `A...B`
+/// and `--all`, `--branches`, `--remotes` or `--tags`. The optional `filter`
+/// is applied while walking, so pages only ever contain matching commits.
+#[tauri::command]
+fn get_commits_for_revspecs(sessions: State<'_, RepoSessions>, path: String, revspecs: Vec<String>, after: Option<String>, page_size: Option<u32>, filter: Option<CommitFilter>) -> Result<CommitPage, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let matcher = filter.as_ref().map(CommitMatcher::new).transpose()?;
+    
+    let page_size = page_size
+        .map(|size| (size as usize).clamp(1, MAX_COMMIT_PAGE_SIZE))
+        .unwrap_or(DEFAULT_COMMIT_PAGE_SIZE);
+    
+    let walk_key = format!("log {:?} {:?}", revspecs, filter);
+    let cursor = parse_cursor(after.as_deref())?;
+    let mut walk = match cursor.and_then(|cursor| sessions.take_walk(&path, &walk_key, cursor)) {
+        Some(walk) => walk,
+        None => {
+            let mut walk = HistoryWalk::start(&repo, &revspecs, git2::Sort::TIME);
+            if let Some(cursor) = cursor {
+                walk.skip_past(cursor, |_| Ok(()))?;
+            }
+            walk
+        }
+    };
     
-    fn get_file_type(name: &str) -> String {
-        let extension = name.split('.').last().unwrap_or("").to_lowercase();
-        match extension.as_str() {
-            "js" | "jsx" => "javascript",
-            "ts" | "tsx" => "typescript", 
-            "py" => "python",
-            "rs" => "rust",
-            "java" => "java",
-            "go" => "go",
-            "c" | "h" => "c",
-            "cpp" | "cc" | "cxx" | "hpp" => "cpp",
-            "css" | "scss" | "sass" => "css",
-            "html" | "htm" => "html",
-            "json" => "json",
-            "yaml" | "yml" => "yaml",
-            "md" | "markdown" => "markdown",
-            "sh" | "bash" | "zsh" => "shell",
-            "sql" => "sql",
-            "xml" => "xml",
-            "toml" => "toml",
-            "ini" | "cfg" | "conf" => "config",
-            "dockerfile" => "docker",
-            "gitignore" => "git",
-            "txt" => "text",
-            _ => "file"
-        }.to_string()
+    let page = collect_commit_page(&repo, &mut walk, page_size, matcher.as_ref())?;
+    if let Some(next_cursor) = &page.next_cursor {
+        let next_cursor = git2::Oid::from_str(next_cursor).map_err(|e| e.to_string())?;
+        sessions.keep_walk(&path, walk_key, next_cursor, walk);
     }
     
-    fn build_tree_recursive(
-        repo: &git2::Repository,
-        tree: &git2::Tree,
-        base_path: &str,
-    ) -> Result<Vec<FileTreeItem>, String> {
-        let mut items = Vec::new();
+    Ok(page)
+}
+
+fn parse_cursor(after: Option<&str>) -> Result<Option<git2::Oid>, String> {
+    after
+        .map(|id| git2::Oid::from_str(id).map_err(|e| format!("Invalid cursor '{}': {}", id, e)))
+        .transpose()
+}
+
+/// A revision walk running on its own thread, which hands out commit ids as
+/// they are read. Unlike a `git2::Revwalk` it doesn't borrow the repository,
+/// so it can be paused after a page and resumed by the next one instead of
+/// walking again from the tips.
+struct HistoryWalk {
+    commits: std::sync::mpsc::Receiver<Result<git2::Oid, String>>,
+    pending: Option<git2::Oid>, // Read past the end of the last page, returned first on resume
+    layout: GraphLayout, // Lanes of the commit graph so far, unused by the other walks
+}
+
+impl HistoryWalk {
+    fn start(repo: &git2::Repository, revspecs: &[String], sorting: git2::Sort) -> Self {
+        let git_dir = repo.path().to_path_buf();
+        let revspecs = revspecs.to_vec();
+        // Run at most a page ahead of the reader
+        let (sender, commits) = std::sync::mpsc::sync_channel(DEFAULT_COMMIT_PAGE_SIZE);
         
-        for entry in tree.iter() {
-            let name = entry.name().unwrap_or("unknown").to_string();
-            let current_path = if base_path.is_empty() {
-                name.clone()
-            } else {
-                format!("{}/{}", base_path, name)
-            };
-            
-            let object = entry.to_object(repo).map_err(|e| e.to_string())?;
-            
-            match object.kind() {
-                Some(git2::ObjectType::Tree) => {
-                    let subtree = object.as_tree().unwrap();
-                    let children = build_tree_recursive(repo, subtree, &current_path)?;
-                    
-                    items.push(FileTreeItem {
-                        name,
-                        path: current_path,
-                        is_directory: true,
-                        children: Some(children),
-                        size: None,
-                        file_type: "folder".to_string(),
-                    });
-                }
-                Some(git2::ObjectType::Blob) => {
-                    let blob = object.as_blob().unwrap();
-                    let file_type = get_file_type(&name);
-                    
-                    items.push(FileTreeItem {
-                        name: name.clone(),
-                        path: current_path,
-                        is_directory: false,
-                        children: None,
-                        size: Some(blob.size() as u64),
-                        file_type,
-                    });
+        std::thread::spawn(move || {
+            let walk = || -> Result<(), String> {
+                let repo = git2::Repository::open(&git_dir).map_err(|e| e.to_string())?;
+                let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
+                push_revspecs(&repo, &mut revwalk, &revspecs)?;
+                revwalk.set_sorting(sorting).map_err(|e| e.to_string())?;
+                
+                for oid in revwalk {
+                    // The reader is gone once the walk is dropped
+                    if sender.send(oid.map_err(|e| e.to_string())).is_err() {
+                        return Ok(());
+                    }
                 }
-                _ => {}
-            }
-        }
-        
-        // Sort items: directories first, then files, both alphabetically
-        items.sort_by(|a, b| {
-            match (a.is_directory, b.is_directory) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                Ok(())
+            };
+            if let Err(e) = walk() {
+                let _ = sender.send(Err(e));
             }
         });
         
-        Ok(items)
+        HistoryWalk {
+            commits,
+            pending: None,
+            layout: GraphLayout::default(),
+        }
     }
     
-    build_tree_recursive(&repo, &tree, "")
-}
-
-#[tauri::command]
-fn open_file_in_editor(repo_path: String, commit_id: String, file_path: String) -> Result<(), String> {
-    use std::process::Command;
-    use std::fs;
-    use std::io::Write;
-    
-    let repo_path_obj = Path::new(&repo_path);
-    let repo = git2::Repository::open(repo_path_obj).map_err(|e| e.to_string())?;
-    
-    // Get the commit
-    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
-    let tree = commit.tree().map_err(|e| e.to_string())?;
-    
-    // Find the file in the tree
-    let tree_entry = tree.get_path(Path::new(&file_path)).map_err(|e| {
-        format!("File '{}' not found in commit '{}': {}", file_path, commit_id, e)
-    })?;
-    
-    // Get the blob
-    let blob = repo.find_blob(tree_entry.id()).map_err(|e| e.to_string())?;
-    
-    // Check if file is binary
-    if blob.is_binary() {
-        return Err("Cannot open binary files in text editor".to_string());
+    /// Consumes everything up to and including `cursor`, passing each
+    /// skipped id to `on_skipped`.
+    fn skip_past(&mut self, cursor: git2::Oid, mut on_skipped: impl FnMut(git2::Oid) -> Result<(), String>) -> Result<(), String> {
+        for oid in self.by_ref() {
+            let oid = oid?;
+            on_skipped(oid)?;
+            if oid == cursor {
+                return Ok(());
+            }
+        }
+        Err(format!("Cursor commit '{}' is not part of this history", cursor))
     }
     
-    // Get file content
-    let content = String::from_utf8(blob.content().to_vec()).map_err(|e| format!("File is not valid UTF-8: {}", e))?;
-    
-    // Create temporary file
-    let temp_dir = std::env::temp_dir();
-    let file_name = Path::new(&file_path).file_name().unwrap_or_else(|| std::ffi::OsStr::new("temp_file"));
-    let temp_file_path = temp_dir.join(format!("git_viewer_{}_{}", commit_id[..8].to_string(), file_name.to_string_lossy()));
-    
-    // Write content to temporary file
-    let mut temp_file = fs::File::create(&temp_file_path).map_err(|e| format!("Failed to create temporary file: {}", e))?;
-    temp_file.write_all(content.as_bytes()).map_err(|e| format!("Failed to write to temporary file: {}", e))?;
-    
-    let temp_file_str = temp_file_path.to_str().ok_or("Invalid temporary file path")?;
-    
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg("-a")
-            .arg("TextEdit")
-            .arg(temp_file_str)
-            .spawn()
-            .map_err(|e| format!("Failed to open file in TextEdit: {}", e))?;
+    /// Puts back a commit that was read but not used, so the next page starts with it.
+    fn put_back(&mut self, oid: git2::Oid) {
+        self.pending = Some(oid);
     }
+}
+
+impl Iterator for HistoryWalk {
+    type Item = Result<git2::Oid, String>;
     
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("notepad")
-            .arg(temp_file_str)
-            .spawn()
-            .map_err(|e| format!("Failed to open file in Notepad: {}", e))?;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.pending.take() {
+            Some(oid) => Some(Ok(oid)),
+            None => self.commits.recv().ok(),
+        }
+    }
+}
+
+fn push_revspecs(repo: &git2::Repository, revwalk: &mut git2::Revwalk, revspecs: &[String]) -> Result<(), String> {
+    if revspecs.is_empty() {
+        return revwalk.push_head().map_err(|e| e.to_string());
     }
     
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("gedit")
-            .arg(temp_file_str)
-            .spawn()
-            .map_err(|e| format!("Failed to open file in gedit: {}", e))?;
+    for revspec in revspecs.iter().map(|spec| spec.trim()) {
+        let glob = match revspec {
+            "--all" => Some("*"),
+            "--branches" => Some("heads"),
+            "--remotes" => Some("remotes"),
+            "--tags" => Some("tags"),
+            _ => None,
+        };
+        if let Some(glob) = glob {
+            revwalk.push_glob(glob).map_err(|e| e.to_string())?;
+            // --all also covers a detached HEAD
+            if revspec == "--all" && repo.head_detached().unwrap_or(false) {
+                revwalk.push_head().map_err(|e| e.to_string())?;
+            }
+            continue;
+        }
+        
+        if let Some(excluded) = revspec.strip_prefix('^') {
+            revwalk.hide(resolve_commit_id(repo, excluded)?).map_err(|e| e.to_string())?;
+            continue;
+        }
+        
+        if !revspec.contains("..") {
+            revwalk.push(resolve_commit_id(repo, revspec)?).map_err(|e| e.to_string())?;
+            continue;
+        }
+        
+        // A..B and A...B, where a missing side means HEAD
+        let range = repo.revparse(revspec).map_err(|e| format!("Invalid revision range '{}': {}", revspec, e))?;
+        let (Some(from), Some(to)) = (range.from(), range.to()) else {
+            return Err(format!("Invalid revision range '{}'", revspec));
+        };
+        let from = from.peel_to_commit().map_err(|e| e.to_string())?.id();
+        let to = to.peel_to_commit().map_err(|e| e.to_string())?.id();
+        
+        if range.mode().contains(git2::RevparseMode::MERGE_BASE) {
+            // Commits on either side, but not on both. Criss-cross merges
+            // have several best merge bases and all of them are shared.
+            revwalk.push(from).map_err(|e| e.to_string())?;
+            revwalk.push(to).map_err(|e| e.to_string())?;
+            match repo.merge_bases(from, to) {
+                Ok(bases) => {
+                    for base in bases.iter() {
+                        revwalk.hide(*base).map_err(|e| e.to_string())?;
+                    }
+                }
+                // Unrelated histories share nothing
+                Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        } else {
+            revwalk.push(to).map_err(|e| e.to_string())?;
+            revwalk.hide(from).map_err(|e| e.to_string())?;
+        }
     }
     
     Ok(())
 }
 
-#[tauri::command]
-fn get_staged_changes(path: String) -> Result<Vec<StagedChange>, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
-    
-    let mut staged_changes = Vec::new();
-    
-    // Use Git's status functionality to get staged files
-    let mut status_options = git2::StatusOptions::new();
-    status_options.include_untracked(false);
-    status_options.include_ignored(false);
-    
-    let statuses = repo.statuses(Some(&mut status_options)).map_err(|e| e.to_string())?;
+fn resolve_commit_id(repo: &git2::Repository, revision: &str) -> Result<git2::Oid, String> {
+    repo.revparse_single(revision)
+        .and_then(|object| object.peel_to_commit())
+        .map(|commit| commit.id())
+        .map_err(|e| match e.code() {
+            git2::ErrorCode::NotFound => format!("Unknown revision '{}'", revision),
+            _ => format!("Cannot resolve '{}': {}", revision, e.message()),
+        })
+}
+
+/// Reads one page of commits from `revwalk`, starting right after the commit
+/// `after` (or at the beginning of the walk when no cursor is given). Commits
+/// rejected by `matcher` are skipped without counting towards the page.
+fn collect_commit_page(repo: &git2::Repository, walk: &mut HistoryWalk, page_size: usize, matcher: Option<&CommitMatcher>) -> Result<CommitPage, String> {
+    let mut commits = Vec::new();
+    let mut has_more = false;
     
-    for status_entry in statuses.iter() {
-        let file_path = status_entry.path().unwrap_or("unknown");
-        let status_flags = status_entry.status();
+    while let Some(oid) = walk.next() {
+        let oid = oid?;
         
-        // Check if the file is staged (in index)
-        if status_flags.contains(git2::Status::INDEX_NEW) {
-            staged_changes.push(StagedChange {
-                path: file_path.to_string(),
-                status: "added".to_string(),
-                old_path: None,
-            });
-        } else if status_flags.contains(git2::Status::INDEX_MODIFIED) {
-            staged_changes.push(StagedChange {
-                path: file_path.to_string(),
-                status: "modified".to_string(),
-                old_path: None,
-            });
-        } else if status_flags.contains(git2::Status::INDEX_DELETED) {
-            staged_changes.push(StagedChange {
-                path: file_path.to_string(),
-                status: "deleted".to_string(),
-                old_path: None,
-            });
-        } else if status_flags.contains(git2::Status::INDEX_RENAMED) {
-            staged_changes.push(StagedChange {
-                path: file_path.to_string(),
-                status: "renamed".to_string(),
-                old_path: None, // TODO: Get the old path for renames
-            });
-        } else if status_flags.contains(git2::Status::INDEX_TYPECHANGE) {
-            staged_changes.push(StagedChange {
-                path: file_path.to_string(),
-                status: "modified".to_string(),
-                old_path: None,
-            });
+        let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+        if matcher.is_some_and(|matcher| !matcher.matches(&commit)) {
+            continue;
+        }
+        
+        if commits.len() >= page_size {
+            walk.put_back(oid);
+            has_more = true;
+            break;
         }
+        
+        commits.push(to_git_commit(&commit));
     }
     
-    Ok(staged_changes)
+    let next_cursor = if has_more {
+        commits.last().map(|commit| commit.id.clone())
+    } else {
+        None
+    };
+    
+    Ok(CommitPage {
+        commits,
+        next_cursor,
+        has_more,
+    })
 }
 
 #[tauri::command]
-fn get_staged_file_diff(path: String, file_path: String) -> Result<FileDiff, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| format!("Failed to open repository: {}", e))?;
-    
-    // Get the index (staging area)
-    let index = repo.index().map_err(|e| format!("Failed to get index: {}", e))?;
-    
-    // Get HEAD tree for comparison (if it exists)
-    let head_tree = match repo.head() {
-        Ok(head) => {
-            let head_commit = head.peel_to_commit().map_err(|e| format!("Failed to get HEAD commit: {}", e))?;
-            Some(head_commit.tree().map_err(|e| format!("Failed to get HEAD tree: {}", e))?)
+fn get_commit_graph(sessions: State<'_, RepoSessions>, path: String, refs: Vec<String>, after: Option<String>, page_size: Option<u32>) -> Result<CommitGraphPage, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let page_size = page_size
+        .map(|size| (size as usize).clamp(1, MAX_COMMIT_PAGE_SIZE))
+        .unwrap_or(DEFAULT_COMMIT_PAGE_SIZE);
+    
+    let walk_key = format!("graph {:?}", refs);
+    let cursor = parse_cursor(after.as_deref())?;
+    let mut walk = match cursor.and_then(|cursor| sessions.take_walk(&path, &walk_key, cursor)) {
+        Some(walk) => walk,
+        None => {
+            // Children must come before their parents for the lanes to connect
+            let mut walk = HistoryWalk::start(&repo, &refs, git2::Sort::TOPOLOGICAL | git2::Sort::TIME);
+            if let Some(cursor) = cursor {
+                // Earlier pages are laid out again, but not returned, so that
+                // lanes line up with the rows the frontend already has
+                let mut layout = GraphLayout::default();
+                walk.skip_past(cursor, |oid| {
+                    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+                    let parents: Vec<git2::Oid> = commit.parent_ids().collect();
+                    layout.place(oid, &parents);
+                    Ok(())
+                })?;
+                walk.layout = layout;
+            }
+            walk
         }
-        Err(_) => None, // Repository has no commits yet
     };
     
-    // Create diff options
-    let mut diff_opts = git2::DiffOptions::new();
-    diff_opts.context_lines(3);
-    diff_opts.max_size(1024 * 1024); // 1MB limit
-    diff_opts.pathspec(file_path.clone());
+    let mut rows = Vec::new();
+    let mut lane_count = 0;
+    let mut has_more = false;
     
-    // Create diff between HEAD and index (staged changes)
-    let diff = repo.diff_tree_to_index(
-        head_tree.as_ref(),
-        Some(&index),
-        Some(&mut diff_opts)
-    ).map_err(|e| format!("Failed to create diff: {}", e))?;
+    while let Some(oid) = walk.next() {
+        let oid = oid?;
+        if rows.len() >= page_size {
+            walk.put_back(oid);
+            has_more = true;
+            break;
+        }
+        
+        let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+        let parents: Vec<git2::Oid> = commit.parent_ids().collect();
+        let (lane, edges) = walk.layout.place(oid, &parents);
+        
+        let widest_edge = edges.iter().map(|edge| edge.from_lane.max(edge.to_lane) + 1).max().unwrap_or(0);
+        lane_count = lane_count.max(lane + 1).max(widest_edge);
+        
+        rows.push(GraphRow {
+            commit: to_git_commit(&commit),
+            parents: parents.iter().map(|parent| parent.to_string()).collect(),
+            lane,
+            edges,
+        });
+    }
     
-    // Find the specific file in the diff
-    let mut file_found = false;
-    let mut file_status = "unknown";
-    let mut is_binary = false;
+    let next_cursor = if has_more {
+        rows.last().map(|row| row.commit.id.clone())
+    } else {
+        None
+    };
+    if let Some(next_cursor) = &next_cursor {
+        let next_cursor = git2::Oid::from_str(next_cursor).map_err(|e| e.to_string())?;
+        sessions.keep_walk(&path, walk_key, next_cursor, walk);
+    }
     
-    // First pass: find if the file exists in this diff
-    for (_delta_idx, delta) in diff.deltas().enumerate() {
-        let delta_path = delta.new_file().path()
-            .or_else(|| delta.old_file().path())
-            .and_then(|p| p.to_str())
-            .unwrap_or("unknown");
+    Ok(CommitGraphPage {
+        rows,
+        lane_count,
+        next_cursor,
+        has_more,
+    })
+}
+
+/// Assigns commits to lanes as they come out of a topological walk. Each lane
+/// holds the commit it is waiting for, and no commit is waited for in two
+/// lanes, so a branch joins the lane of its fork point as soon as it reaches it.
+#[derive(Default)]
+struct GraphLayout {
+    lanes: Vec<Option<git2::Oid>>,
+}
+
+impl GraphLayout {
+    fn place(&mut self, oid: git2::Oid, parents: &[git2::Oid]) -> (u32, Vec<GraphEdge>) {
+        // Branch tips nobody is waiting for start a new lane
+        let lane = match self.lanes.iter().position(|expected| *expected == Some(oid)) {
+            Some(lane) => lane,
+            None => self.free_lane(),
+        };
+        self.lanes[lane] = None;
         
-        if delta_path == file_path {
-            file_found = true;
-            file_status = match delta.status() {
-                git2::Delta::Added => "added",
-                git2::Delta::Deleted => "deleted", 
-                git2::Delta::Modified => "modified",
-                git2::Delta::Renamed => "renamed",
-                git2::Delta::Copied => "copied",
-                _ => "unknown",
+        let mut edges: Vec<GraphEdge> = self.lanes.iter().enumerate()
+            .filter(|(_, expected)| expected.is_some())
+            .map(|(index, _)| graph_edge(index, index, "pass"))
+            .collect();
+        
+        for (index, parent) in parents.iter().enumerate() {
+            let target = match self.lanes.iter().position(|expected| *expected == Some(*parent)) {
+                Some(existing) => existing,
+                None => {
+                    let target = if index == 0 { lane } else { self.free_lane() };
+                    self.lanes[target] = Some(*parent);
+                    target
+                }
             };
-            is_binary = delta.new_file().is_binary() || delta.old_file().is_binary();
-            break;
+            edges.push(graph_edge(lane, target, if index == 0 { "parent" } else { "merge" }));
         }
+        
+        while self.lanes.last() == Some(&None) {
+            self.lanes.pop();
+        }
+        
+        (lane as u32, edges)
     }
     
-    if !file_found {
-        return Err(format!("File '{}' not found in staged changes", file_path));
+    fn free_lane(&mut self) -> usize {
+        match self.lanes.iter().position(|expected| expected.is_none()) {
+            Some(lane) => lane,
+            None => {
+                self.lanes.push(None);
+                self.lanes.len() - 1
+            }
+        }
     }
-    
-    if is_binary {
-        return Ok(FileDiff {
-            path: file_path,
-            status: file_status.to_string(),
-            old_content: None,
-            new_content: None,
-            diff_lines: Vec::new(),
-            is_binary: true,
-        });
+}
+
+fn graph_edge(from_lane: usize, to_lane: usize, edge_type: &str) -> GraphEdge {
+    GraphEdge {
+        from_lane: from_lane as u32,
+        to_lane: to_lane as u32,
+        edge_type: edge_type.to_string(),
     }
+}
+
+/// Lists the commits that changed `file_path`, newest first, like
+/// `git log --follow`. A directory path matches changes to any file below it,
+/// but renames are only followed for single files. When paging, pass the
+/// previous page's `next_cursor` and `next_path` as `after` and `after_path`,
+/// so commits before the cursor never have to be diffed again.
+#[tauri::command]
+fn get_file_history(sessions: State<'_, RepoSessions>, path: String, file_path: String, revision: Option<String>, after: Option<String>, after_path: Option<String>, page_size: Option<u32>) -> Result<FileHistoryPage, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let page_size = page_size
+        .map(|size| (size as usize).clamp(1, MAX_COMMIT_PAGE_SIZE))
+        .unwrap_or(DEFAULT_COMMIT_PAGE_SIZE);
+    
+    let revspecs: Vec<String> = revision.into_iter().collect();
+    let walk_key = format!("history {:?} {:?}", file_path, revspecs);
+    let cursor = parse_cursor(after.as_deref())?;
+    let mut walk = match cursor.and_then(|cursor| sessions.take_walk(&path, &walk_key, cursor)) {
+        Some(walk) => walk,
+        None => {
+            let mut walk = HistoryWalk::start(&repo, &revspecs, git2::Sort::TOPOLOGICAL | git2::Sort::TIME);
+            if let Some(cursor) = cursor {
+                walk.skip_past(cursor, |_| Ok(()))?;
+            }
+            walk
+        }
+    };
     
-    // Generate patch for text files
-    let mut patch_lines = Vec::new();
+    // Renames before the cursor were already followed by the previous page
+    let mut current_path = after_path.unwrap_or(file_path).trim_end_matches('/').to_string();
+    let mut entries = Vec::new();
+    let mut has_more = false;
     
-    for (delta_idx, delta) in diff.deltas().enumerate() {
-        let delta_path = delta.new_file().path()
-            .or_else(|| delta.old_file().path())
-            .and_then(|p| p.to_str())
-            .unwrap_or("unknown");
+    while let Some(oid) = walk.next() {
+        let oid = oid?;
+        let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+        let Some(entry) = file_history_entry(&repo, &commit, &current_path)? else {
+            continue;
+        };
         
-        if delta_path == file_path {
-            let patch = git2::Patch::from_diff(&diff, delta_idx).map_err(|e| format!("Failed to create patch: {}", e))?;
-            
-            if let Some(patch) = patch {
-                for hunk_idx in 0..patch.num_hunks() {
-                    let (_hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| format!("Failed to get hunk: {}", e))?;
-                    
-                    for line_idx in 0..hunk_lines {
-                        let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| format!("Failed to get line: {}", e))?;
-                        
-                        let line_content = String::from_utf8_lossy(line.content()).trim_end_matches('\n').to_string();
-                        let line_type = match line.origin() {
-                            '+' => "addition",
-                            '-' => "deletion", 
-                            ' ' => "context",
-                            _ => "context",
-                        };
-                        
-                        patch_lines.push(DiffLine {
-                            line_type: line_type.to_string(),
-                            content: line_content,
-                            old_line_number: line.old_lineno(),
-                            new_line_number: line.new_lineno(),
-                        });
-                    }
-                }
-            }
+        if entries.len() >= page_size {
+            walk.put_back(oid);
+            has_more = true;
             break;
         }
+        
+        // Older commits know the file under its previous name
+        if let Some(old_path) = &entry.old_path {
+            current_path = old_path.clone();
+        }
+        entries.push(entry);
     }
     
-    Ok(FileDiff {
-        path: file_path,
-        status: file_status.to_string(),
-        old_content: None,
-        new_content: None,
-        diff_lines: patch_lines,
-        is_binary: false,
+    let next_cursor = if has_more {
+        entries.last().map(|entry| entry.commit.id.clone())
+    } else {
+        None
+    };
+    if let Some(next_cursor) = &next_cursor {
+        let next_cursor = git2::Oid::from_str(next_cursor).map_err(|e| e.to_string())?;
+        sessions.keep_walk(&path, walk_key, next_cursor, walk);
+    }
+    
+    Ok(FileHistoryPage {
+        entries,
+        next_cursor,
+        next_path: has_more.then_some(current_path),
+        has_more,
     })
 }
 
-#[tauri::command]
-fn get_stashes(path: String) -> Result<Vec<GitStash>, String> {
-    let repo_path = Path::new(&path);
-    let mut repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+// Returns how `commit` changed `file_path`, or None when it left it alone
+fn file_history_entry(repo: &git2::Repository, commit: &git2::Commit, file_path: &str) -> Result<Option<FileHistoryEntry>, String> {
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+    let entry_at = |tree: &git2::Tree| tree.get_path(Path::new(file_path)).ok();
+    let current = entry_at(&tree);
     
-    let mut stashes = Vec::new();
+    // Like git log, a merge only counts when the path differs from every parent
+    let mut parent_trees = Vec::new();
+    for parent in commit.parents() {
+        parent_trees.push(parent.tree().map_err(|e| e.to_string())?);
+    }
+    if parent_trees.iter().any(|parent_tree| entry_at(parent_tree).map(|e| e.id()) == current.as_ref().map(|e| e.id())) {
+        return Ok(None);
+    }
+    if parent_trees.is_empty() && current.is_none() {
+        return Ok(None);
+    }
     
-    // Use git2's stash foreach to iterate through stashes
-    let repo_for_commit = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let parent_tree = parent_trees.first();
+    let previous = parent_tree.and_then(entry_at);
+    let is_directory = current.as_ref().or(previous.as_ref()).and_then(|entry| entry.kind()) == Some(git2::ObjectType::Tree);
     
-    repo.stash_foreach(|index, message, stash_id| {
-        // Get the stash commit
-        if let Ok(stash_commit) = repo_for_commit.find_commit(*stash_id) {
-            let author = stash_commit.author();
-            let timestamp = stash_commit.time();
-            
-            // Format the date
-            let datetime = chrono::DateTime::from_timestamp(timestamp.seconds(), 0)
-                .unwrap_or_else(|| chrono::DateTime::from_timestamp(0, 0).unwrap());
-            let formatted_date = datetime.format("%Y-%m-%d %H:%M:%S").to_string();
-            
-            stashes.push(GitStash {
-                index: index as u32,
-                message: message.to_string(),
-                commit_id: stash_id.to_string(),
-                author: format!("{} <{}>", 
-                    author.name().unwrap_or("Unknown"), 
-                    author.email().unwrap_or("unknown@email.com")),
-                date: formatted_date,
-            });
+    // A file that is new here may have been renamed, which only shows up
+    // when comparing the whole tree
+    let follow_rename = !is_directory && previous.is_none() && parent_tree.is_some();
+    let mut diff_opts = git2::DiffOptions::new();
+    if !follow_rename {
+        diff_opts.pathspec(file_path);
+    }
+    let mut diff = repo.diff_tree_to_tree(parent_tree, Some(&tree), Some(&mut diff_opts)).map_err(|e| e.to_string())?;
+    if follow_rename {
+        diff.find_similar(None).map_err(|e| e.to_string())?;
+    }
+    
+    let mut status = if previous.is_none() {
+        "added"
+    } else if current.is_none() {
+        "deleted"
+    } else {
+        "modified"
+    };
+    let mut old_path = None;
+    let mut additions = 0;
+    let mut deletions = 0;
+    
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        if !is_directory {
+            let delta_path = delta.new_file().path()
+                .or_else(|| delta.old_file().path())
+                .and_then(|p| p.to_str());
+            if delta_path != Some(file_path) {
+                continue;
+            }
+            status = delta_status(delta.status());
+            if delta.status() == git2::Delta::Renamed {
+                old_path = delta.old_file().path().and_then(|p| p.to_str()).map(|p| p.to_string());
+            }
         }
-        true // Continue iteration
-    }).map_err(|e| e.to_string())?;
+        
+        let (delta_additions, delta_deletions) = delta_line_stats(&diff, delta_idx)?;
+        additions += delta_additions;
+        deletions += delta_deletions;
+    }
     
-    Ok(stashes)
+    Ok(Some(FileHistoryEntry {
+        commit: to_git_commit(commit),
+        path: file_path.to_string(),
+        old_path,
+        status: status.to_string(),
+        additions,
+        deletions,
+    }))
 }
 
-#[tauri::command]
-fn get_stash_diff(path: String, stash_index: u32) -> Result<Vec<FileChange>, String> {
-    let repo_path = Path::new(&path);
-    let mut repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
-    
-    // Get the stash commit by index
-    let mut stash_commit_id = None;
-    let mut current_index = 0;
+struct CommitMatcher {
+    author: Option<regex::Regex>,
+    committer: Option<regex::Regex>,
+    since: Option<i64>,
+    until: Option<i64>,
+    message: Option<regex::Regex>,
+}
+
+impl CommitMatcher {
+    fn new(filter: &CommitFilter) -> Result<Self, String> {
+        let compile = |pattern: &Option<String>, what: &str, case_insensitive: bool| {
+            match pattern.as_deref().filter(|pattern| !pattern.is_empty()) {
+                Some(pattern) => regex::RegexBuilder::new(pattern)
+                    .case_insensitive(case_insensitive)
+                    .build()
+                    .map(Some)
+                    .map_err(|e| format!("Invalid {} pattern: {}", what, e)),
+                None => Ok(None),
+            }
+        };
+        
+        Ok(CommitMatcher {
+            author: compile(&filter.author, "author", true)?,
+            committer: compile(&filter.committer, "committer", true)?,
+            since: filter.since,
+            until: filter.until,
+            message: compile(&filter.message, "message", false)?,
+        })
+    }
     
-    repo.stash_foreach(|index, _message, stash_id| {
-        if index as u32 == stash_index {
-            stash_commit_id = Some(*stash_id);
-            false // Stop iteration
-        } else {
-            current_index = index;
-            true // Continue iteration
+    fn matches(&self, commit: &git2::Commit) -> bool {
+        let time = commit.time().seconds();
+        if self.since.is_some_and(|since| time < since) || self.until.is_some_and(|until| time > until) {
+            return false;
         }
-    }).map_err(|e| e.to_string())?;
-    
-    let stash_oid = stash_commit_id.ok_or("Stash not found")?;
-    let stash_commit = repo.find_commit(stash_oid).map_err(|e| e.to_string())?;
-    
-    // Get the parent commit (the commit the stash was based on)
-    let parent_commit = stash_commit.parent(0).map_err(|e| e.to_string())?;
-    
-    // Create diff between parent and stash
-    let parent_tree = parent_commit.tree().map_err(|e| e.to_string())?;
-    let stash_tree = stash_commit.tree().map_err(|e| e.to_string())?;
-    
-    let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&stash_tree), None)
-        .map_err(|e| e.to_string())?;
-    
-    let mut changes = Vec::new();
-    
-    for (_, delta) in diff.deltas().enumerate() {
-        let file_path = delta.new_file().path()
-            .or_else(|| delta.old_file().path())
-            .and_then(|p| p.to_str())
-            .unwrap_or("unknown");
         
-        let status = match delta.status() {
-            git2::Delta::Added => "added",
-            git2::Delta::Deleted => "deleted", 
-            git2::Delta::Modified => "modified",
-            git2::Delta::Renamed => "renamed",
-            git2::Delta::Copied => "copied",
-            _ => "unknown",
+        let identity = |signature: git2::Signature| {
+            format!("{} <{}>", signature.name().unwrap_or(""), signature.email().unwrap_or(""))
         };
+        if let Some(author) = &self.author {
+            if !author.is_match(&identity(commit.author())) {
+                return false;
+            }
+        }
+        if let Some(committer) = &self.committer {
+            if !committer.is_match(&identity(commit.committer())) {
+                return false;
+            }
+        }
+        if let Some(message) = &self.message {
+            if !message.is_match(commit.message().unwrap_or("")) {
+                return false;
+            }
+        }
         
-        // Get line count stats
-        let stats = diff.stats().map_err(|e| e.to_string())?;
-        
-        changes.push(FileChange {
-            path: file_path.to_string(),
-            status: status.to_string(),
-            additions: stats.insertions() as u32,
-            deletions: stats.deletions() as u32,
-        });
+        true
+    }
+}
+
+fn to_git_commit(commit: &git2::Commit) -> GitCommit {
+    let oid = commit.id();
+    let message = commit.message().unwrap_or("No message").to_string();
+    let author = commit.author();
+    let author_name = author.name().unwrap_or("Unknown").to_string();
+    let date = commit.time();
+    let date_str = format!("{}", chrono::DateTime::from_timestamp(date.seconds(), 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d %H:%M:%S"));
+    
+    GitCommit {
+        id: oid.to_string(),
+        message: message.lines().next().unwrap_or(&message).to_string(),
+        author: author_name,
+        date: date_str,
+        short_id: oid.to_string()[0..8].to_string(),
     }
-    
-    Ok(changes)
 }
 
 #[tauri::command]
-fn get_stash_file_diff(path: String, stash_index: u32, file_path: String) -> Result<FileDiff, String> {
-    let repo_path = Path::new(&path);
-    let mut repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+fn get_commit_changes(sessions: State<'_, RepoSessions>, path: String, commit_id: String, parent_index: Option<u32>) -> Result<Vec<FileChange>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    // Get the stash commit by index
-    let mut stash_commit_id = None;
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
     
-    repo.stash_foreach(|index, _message, stash_id| {
-        if index as u32 == stash_index {
-            stash_commit_id = Some(*stash_id);
-            false // Stop iteration
-        } else {
-            true // Continue iteration
-        }
-    }).map_err(|e| e.to_string())?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+    let parent_tree = commit_parent_tree(&commit, parent_index)?;
     
-    let stash_oid = stash_commit_id.ok_or("Stash not found")?;
-    let stash_commit = repo.find_commit(stash_oid).map_err(|e| e.to_string())?;
+    let mut diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&tree),
+        None
+    ).map_err(|e| e.to_string())?;
     
-    // Get the parent commit
-    let parent_commit = stash_commit.parent(0).map_err(|e| e.to_string())?;
+    diff.find_similar(None).map_err(|e| e.to_string())?;
     
-    // Create diff between parent and stash for specific file
-    let parent_tree = parent_commit.tree().map_err(|e| e.to_string())?;
-    let stash_tree = stash_commit.tree().map_err(|e| e.to_string())?;
+    collect_file_changes(&diff)
+}
+
+#[tauri::command]
+fn get_commit_stats(sessions: State<'_, RepoSessions>, path: String, commit_id: String, parent_index: Option<u32>) -> Result<DiffSummary, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    let mut diff_opts = git2::DiffOptions::new();
-    diff_opts.context_lines(3);
-    diff_opts.pathspec(file_path.clone());
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
     
-    let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&stash_tree), Some(&mut diff_opts))
-        .map_err(|e| e.to_string())?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+    let parent_tree = commit_parent_tree(&commit, parent_index)?;
     
-    // Find the specific file in the diff
-    let mut file_found = false;
-    let mut file_status = "unknown";
-    let mut is_binary = false;
+    let mut diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&tree),
+        None
+    ).map_err(|e| e.to_string())?;
     
-    for (_delta_idx, delta) in diff.deltas().enumerate() {
-        let delta_path = delta.new_file().path()
+    diff.find_similar(None).map_err(|e| e.to_string())?;
+    
+    let stats = diff.stats().map_err(|e| e.to_string())?;
+    
+    Ok(DiffSummary {
+        files_changed: stats.files_changed() as u32,
+        insertions: stats.insertions() as u32,
+        deletions: stats.deletions() as u32,
+    })
+}
+
+#[tauri::command]
+fn get_commit_details(sessions: State<'_, RepoSessions>, path: String, commit_id: String) -> Result<CommitDetails, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    
+    let message = String::from_utf8_lossy(commit.message_bytes()).to_string();
+    let (summary, body) = match message.split_once('\n') {
+        Some((summary, body)) => (summary.trim_end().to_string(), body.trim().to_string()),
+        None => (message.trim_end().to_string(), String::new()),
+    };
+    
+    // Messages without a trailer block simply have no trailers
+    let trailers = match git2::message_trailers_strs(&message) {
+        Ok(trailers) => trailers.iter()
+            .map(|(key, value)| CommitTrailer {
+                key: key.to_string(),
+                value: value.to_string(),
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    
+    let mut refs = Vec::new();
+    let head = repo.head().ok();
+    if head.as_ref().and_then(|head| head.target()) == Some(oid) {
+        refs.push("HEAD".to_string());
+    }
+    for reference in repo.references().map_err(|e| e.to_string())? {
+        let reference = reference.map_err(|e| e.to_string())?;
+        if reference.kind() != Some(git2::ReferenceType::Direct) || reference.name() == Some("refs/stash") {
+            continue;
+        }
+        if reference.peel_to_commit().map(|target| target.id()).ok() == Some(oid) {
+            if let Some(name) = reference.shorthand() {
+                refs.push(name.to_string());
+            }
+        }
+    }
+    
+    let author = to_commit_identity(&commit.author());
+    let committer = to_commit_identity(&commit.committer());
+    
+    Ok(CommitDetails {
+        id: oid.to_string(),
+        short_id: oid.to_string()[0..8].to_string(),
+        summary,
+        body,
+        trailers,
+        author,
+        committer,
+        parent_ids: commit.parent_ids().map(|parent| parent.to_string()).collect(),
+        tree_id: commit.tree_id().to_string(),
+        refs,
+    })
+}
+
+fn to_commit_identity(signature: &git2::Signature) -> CommitIdentity {
+    let when = signature.when();
+    let offset = chrono::FixedOffset::east_opt(when.offset_minutes() * 60)
+        .unwrap_or_else(|| chrono::FixedOffset::east_opt(0).unwrap());
+    let local_time = chrono::DateTime::from_timestamp(when.seconds(), 0)
+        .unwrap_or_default()
+        .with_timezone(&offset);
+    
+    CommitIdentity {
+        name: signature.name().unwrap_or("Unknown").to_string(),
+        email: signature.email().unwrap_or("unknown@email.com").to_string(),
+        timestamp: when.seconds(),
+        timezone: offset.to_string(),
+        date: local_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+    }
+}
+
+fn delta_status(status: git2::Delta) -> &'static str {
+    match status {
+        git2::Delta::Added => "added",
+        git2::Delta::Deleted => "deleted",
+        git2::Delta::Modified => "modified",
+        git2::Delta::Renamed => "renamed",
+        git2::Delta::Copied => "copied",
+        git2::Delta::Typechange => "modified",
+        git2::Delta::Untracked => "untracked",
+        git2::Delta::Conflicted => "conflicted",
+        _ => "unknown",
+    }
+}
+
+/// Added and deleted line counts for one delta of `diff`. Binary files count
+/// as no lines.
+fn delta_line_stats(diff: &git2::Diff, delta_idx: usize) -> Result<(u32, u32), String> {
+    match git2::Patch::from_diff(diff, delta_idx).map_err(|e| e.to_string())? {
+        Some(patch) => {
+            let (_context, additions, deletions) = patch.line_stats().map_err(|e| e.to_string())?;
+            Ok((additions as u32, deletions as u32))
+        }
+        None => Ok((0, 0)),
+    }
+}
+
+fn collect_file_changes(diff: &git2::Diff) -> Result<Vec<FileChange>, String> {
+    let mut changes = Vec::new();
+    
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        let path = delta.new_file().path()
             .or_else(|| delta.old_file().path())
             .and_then(|p| p.to_str())
-            .unwrap_or("unknown");
+            .unwrap_or("unknown")
+            .to_string();
         
-        if delta_path == file_path {
-            file_found = true;
-            file_status = match delta.status() {
-                git2::Delta::Added => "added",
-                git2::Delta::Deleted => "deleted", 
-                git2::Delta::Modified => "modified",
-                git2::Delta::Renamed => "renamed",
-                git2::Delta::Copied => "copied",
-                _ => "unknown",
-            };
-            is_binary = delta.new_file().is_binary() || delta.old_file().is_binary();
-            break;
+        let (additions, deletions) = delta_line_stats(diff, delta_idx)?;
+        
+        changes.push(FileChange {
+            path,
+            status: delta_status(delta.status()).to_string(),
+            additions,
+            deletions,
+        });
+    }
+    
+    Ok(changes)
+}
+
+#[tauri::command]
+fn get_file_diff(sessions: State<'_, RepoSessions>, path: String, commit_id: String, file_path: String, parent_index: Option<u32>) -> Result<FileDiff, String> {
+    let handle = sessions.open(&path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let repo = handle.lock();
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| format!("Invalid commit ID: {}", e))?;
+    let commit = repo.find_commit(oid).map_err(|e| format!("Commit not found: {}", e))?;
+    
+    let tree = commit.tree().map_err(|e| format!("Failed to get commit tree: {}", e))?;
+    let parent_tree = commit_parent_tree(&commit, parent_index)?;
+    
+    // Create diff options with limits to prevent large diffs from causing issues
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.context_lines(3);
+    diff_opts.max_size(1024 * 1024); // 1MB limit
+    
+    let diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&tree),
+        Some(&mut diff_opts)
+    ).map_err(|e| format!("Failed to create diff: {}", e))?;
+    
+    build_file_diff(&diff, file_path, "commit changes")
+}
+
+/// Lists the files of a merge commit that differ from every parent, the
+/// files `git show -c` reports. Line stats are against the first parent.
+#[tauri::command]
+fn get_combined_commit_changes(sessions: State<'_, RepoSessions>, path: String, commit_id: String) -> Result<Vec<FileChange>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+    
+    let mut changes = Vec::new();
+    let mut changed_in_other_parents: Option<HashSet<String>> = None;
+    for parent_index in (0..commit.parent_count()).rev() {
+        let parent_tree = commit.parent(parent_index).and_then(|parent| parent.tree()).map_err(|e| e.to_string())?;
+        let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), None).map_err(|e| e.to_string())?;
+        let parent_changes = collect_file_changes(&diff)?;
+        
+        if parent_index == 0 {
+            changes = parent_changes;
+        } else {
+            let paths = parent_changes.into_iter().map(|change| change.path);
+            changed_in_other_parents = Some(match changed_in_other_parents {
+                Some(previous) => paths.filter(|path| previous.contains(path)).collect(),
+                None => paths.collect(),
+            });
         }
     }
     
-    if !file_found {
-        return Err(format!("File '{}' not found in stash changes", file_path));
+    if let Some(paths) = changed_in_other_parents {
+        changes.retain(|change| paths.contains(&change.path));
+    }
+    
+    Ok(changes)
+}
+
+/// Diffs a file of a merge commit against all parents at once, like
+/// `git show -c`. In dense mode only lines that differ from every parent,
+/// and their context, are kept, like `git show --cc`, which leaves out
+/// hunks that were taken unchanged from one side.
+#[tauri::command]
+fn get_combined_file_diff(sessions: State<'_, RepoSessions>, path: String, commit_id: String, file_path: String, dense: Option<bool>) -> Result<CombinedFileDiff, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    
+    let blob_at = |tree: &git2::Tree| -> Result<Option<git2::Blob>, String> {
+        match tree.get_path(Path::new(&file_path)) {
+            Ok(entry) => repo.find_blob(entry.id()).map(Some).map_err(|e| e.to_string()),
+            Err(_) => Ok(None),
+        }
+    };
+    
+    let result = blob_at(&commit.tree().map_err(|e| e.to_string())?)?;
+    let mut parent_blobs = Vec::new();
+    for parent in commit.parents() {
+        parent_blobs.push(blob_at(&parent.tree().map_err(|e| e.to_string())?)?);
+    }
+    if result.is_none() && parent_blobs.iter().all(|blob| blob.is_none()) {
+        return Err(format!("File '{}' not found in commit or its parents", file_path));
     }
     
+    let parent_ids: Vec<String> = commit.parent_ids().map(|parent| parent.to_string()).collect();
+    let is_binary = result.iter().chain(parent_blobs.iter().flatten()).any(|blob| blob.is_binary());
     if is_binary {
-        return Ok(FileDiff {
+        return Ok(CombinedFileDiff {
             path: file_path,
-            status: file_status.to_string(),
-            old_content: None,
-            new_content: None,
+            parent_ids,
             diff_lines: Vec::new(),
             is_binary: true,
         });
     }
     
-    // Generate patch for text files
-    let mut patch_lines = Vec::new();
+    let result_content = result.as_ref().map(|blob| blob.content()).unwrap_or(&[]);
+    let parent_contents: Vec<&[u8]> = parent_blobs.iter().map(|blob| blob.as_ref().map(|blob| blob.content()).unwrap_or(&[])).collect();
+    let diff_lines = combined_diff_lines(result_content, &parent_contents, dense.unwrap_or(true))?;
     
-    for (delta_idx, delta) in diff.deltas().enumerate() {
-        let delta_path = delta.new_file().path()
-            .or_else(|| delta.old_file().path())
-            .and_then(|p| p.to_str())
-            .unwrap_or("unknown");
+    Ok(CombinedFileDiff {
+        path: file_path,
+        parent_ids,
+        diff_lines,
+        is_binary: false,
+    })
+}
+
+fn combined_diff_lines(result: &[u8], parents: &[&[u8]], dense: bool) -> Result<Vec<CombinedDiffLine>, String> {
+    const CONTEXT_LINES: usize = 3;
+    
+    let result_text = String::from_utf8_lossy(result);
+    let result_lines: Vec<&str> = result_text.lines().collect();
+    let parent_count = parents.len();
+    
+    // For every result line, which parents lack it, and for every gap before
+    // a result line, the lines removed from each parent there
+    let mut added = vec![vec![false; parent_count]; result_lines.len()];
+    let mut removed: Vec<Vec<(String, Vec<bool>)>> = vec![Vec::new(); result_lines.len() + 1];
+    
+    for (parent_index, parent) in parents.iter().enumerate() {
+        let mut diff_opts = git2::DiffOptions::new();
+        diff_opts.context_lines(0);
+        let patch = git2::Patch::from_buffers(parent, None, result, None, Some(&mut diff_opts)).map_err(|e| e.to_string())?;
         
-        if delta_path == file_path {
-            let patch = git2::Patch::from_diff(&diff, delta_idx).map_err(|e| format!("Failed to create patch: {}", e))?;
+        for hunk_idx in 0..patch.num_hunks() {
+            let (hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| e.to_string())?;
+            // Without context a hunk's removed lines sit right before its first new line
+            let gap = if hunk.new_lines() == 0 { hunk.new_start() } else { hunk.new_start() - 1 } as usize;
+            let mut matched = 0;
             
-            if let Some(patch) = patch {
-                for hunk_idx in 0..patch.num_hunks() {
-                    let (_hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| format!("Failed to get hunk: {}", e))?;
-                    
-                    for line_idx in 0..hunk_lines {
-                        let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| format!("Failed to get line: {}", e))?;
-                        
-                        let line_content = String::from_utf8_lossy(line.content()).trim_end_matches('\n').to_string();
-                        let line_type = match line.origin() {
-                            '+' => "addition",
-                            '-' => "deletion", 
-                            ' ' => "context",
-                            _ => "context",
-                        };
-                        
-                        patch_lines.push(DiffLine {
-                            line_type: line_type.to_string(),
-                            content: line_content,
-                            old_line_number: line.old_lineno(),
-                            new_line_number: line.new_lineno(),
-                        });
+            for line_idx in 0..hunk_lines {
+                let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| e.to_string())?;
+                match line.origin() {
+                    '+' => {
+                        if let Some(flags) = line.new_lineno().and_then(|n| added.get_mut(n as usize - 1)) {
+                            flags[parent_index] = true;
+                        }
+                    }
+                    '-' => {
+                        let content = String::from_utf8_lossy(line.content()).trim_end_matches(['\n', '\r']).to_string();
+                        let removed_here = &mut removed[gap.min(result_lines.len())];
+                        // Lines removed from several parents are shown once
+                        match removed_here.iter().skip(matched).position(|(text, parents)| *text == content && !parents[parent_index]) {
+                            Some(offset) => {
+                                removed_here[matched + offset].1[parent_index] = true;
+                                matched += offset + 1;
+                            }
+                            None => {
+                                let mut parents = vec![false; parent_count];
+                                parents[parent_index] = true;
+                                removed_here.push((content, parents));
+                                matched = removed_here.len();
+                            }
+                        }
                     }
+                    _ => {}
                 }
             }
-            break;
         }
     }
     
-    Ok(FileDiff {
-        path: file_path,
-        status: file_status.to_string(),
-        old_content: None,
-        new_content: None,
-        diff_lines: patch_lines,
-        is_binary: false,
+    // Lay out removed and result lines in display order. `parents` marks the
+    // parents a line was removed from or is missing in.
+    struct Line {
+        line_type: &'static str,
+        parents: Vec<bool>,
+        content: String,
+        new_line_number: Option<u32>,
+    }
+    let mut lines = Vec::new();
+    for (gap, removed_lines) in removed.into_iter().enumerate() {
+        for (content, parents) in removed_lines {
+            lines.push(Line { line_type: "deletion", parents, content, new_line_number: None });
+        }
+        if let Some(content) = result_lines.get(gap) {
+            let parents = added[gap].clone();
+            let line_type = if parents.iter().any(|&a| a) { "addition" } else { "context" };
+            lines.push(Line { line_type, parents, content: content.to_string(), new_line_number: Some(gap as u32 + 1) });
+        }
+    }
+    
+    let interesting: Vec<bool> = lines.iter()
+        .map(|line| line.line_type != "context" && (!dense || line.parents.iter().all(|&p| p)))
+        .collect();
+    
+    let mut diff_lines = Vec::new();
+    let mut hunk_index = 0;
+    let mut last_shown: Option<usize> = None;
+    for (index, line) in lines.into_iter().enumerate() {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(interesting.len());
+        if !interesting[start..end].iter().any(|&i| i) {
+            continue;
+        }
+        if last_shown.is_some_and(|last| index > last + 1) {
+            hunk_index += 1;
+        }
+        last_shown = Some(index);
+        
+        let marker = if line.line_type == "deletion" { '-' } else { '+' };
+        diff_lines.push(CombinedDiffLine {
+            line_type: line.line_type.to_string(),
+            markers: line.parents.iter().map(|&p| if p { marker } else { ' ' }).collect(),
+            content: line.content,
+            new_line_number: line.new_line_number,
+            hunk_index,
+        });
+    }
+    
+    Ok(diff_lines)
+}
+
+// The tree of the parent a commit's changes are shown against
+fn commit_parent_tree<'r>(commit: &git2::Commit<'r>, parent_index: Option<u32>) -> Result<Option<git2::Tree<'r>>, String> {
+    let parent_count = commit.parent_count();
+    let parent_index = parent_index.unwrap_or(0) as usize;
+    
+    if parent_count == 0 && parent_index == 0 {
+        return Ok(None);
+    }
+    if parent_index >= parent_count {
+        return Err(format!("Commit {} has no parent {} (it has {})", commit.id(), parent_index + 1, parent_count));
+    }
+    
+    let parent = commit.parent(parent_index).map_err(|e| format!("Failed to get parent: {}", e))?;
+    let tree = parent.tree().map_err(|e| format!("Failed to get parent tree: {}", e))?;
+    Ok(Some(tree))
+}
+
+/// Compares two revisions. By default this is `git diff base head`; with
+/// `three_dot` the diff starts from their merge base instead, like
+/// `git diff base...head`, showing only what `head` added since it forked.
+#[tauri::command]
+fn compare_revisions(sessions: State<'_, RepoSessions>, path: String, base: String, head: String, three_dot: Option<bool>) -> Result<RevisionComparison, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let (base_id, head_id, merge_base_id) = resolve_comparison(&repo, &base, &head, three_dot.unwrap_or(false))?;
+    let diff = comparison_diff(&repo, merge_base_id.unwrap_or(base_id), head_id)?;
+    let stats = diff.stats().map_err(|e| e.to_string())?;
+    
+    Ok(RevisionComparison {
+        base_id: base_id.to_string(),
+        head_id: head_id.to_string(),
+        merge_base_id: merge_base_id.map(|oid| oid.to_string()),
+        changes: collect_file_changes(&diff)?,
+        summary: DiffSummary {
+            files_changed: stats.files_changed() as u32,
+            insertions: stats.insertions() as u32,
+            deletions: stats.deletions() as u32,
+        },
     })
 }
 
 #[tauri::command]
-fn get_file_content(path: String, commit_id: String, file_path: String) -> Result<String, String> {
-    let repo_path = Path::new(&path);
-    let repo = git2::Repository::open(repo_path).map_err(|e| e.to_string())?;
+fn get_comparison_file_diff(sessions: State<'_, RepoSessions>, path: String, base: String, head: String, file_path: String, three_dot: Option<bool>) -> Result<FileDiff, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
     
-    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
-    let tree = commit.tree().map_err(|e| e.to_string())?;
+    let (base_id, head_id, merge_base_id) = resolve_comparison(&repo, &base, &head, three_dot.unwrap_or(false))?;
+    let diff = comparison_diff(&repo, merge_base_id.unwrap_or(base_id), head_id)?;
     
-    // Find the file in the tree
-    let tree_entry = tree.get_path(Path::new(&file_path)).map_err(|e| {
-        format!("File '{}' not found in commit '{}': {}", file_path, commit_id, e)
-    })?;
+    build_file_diff(&diff, file_path, &format!("changes between {} and {}", base, head))
+}
+
+fn resolve_comparison(repo: &git2::Repository, base: &str, head: &str, three_dot: bool) -> Result<(git2::Oid, git2::Oid, Option<git2::Oid>), String> {
+    let base_id = resolve_commit_id(repo, base)?;
+    let head_id = resolve_commit_id(repo, head)?;
+    
+    let merge_base_id = if three_dot {
+        let merge_base = repo.merge_base(base_id, head_id).map_err(|e| match e.code() {
+            git2::ErrorCode::NotFound => format!("'{}' and '{}' have no common history", base, head),
+            _ => e.to_string(),
+        })?;
+        Some(merge_base)
+    } else {
+        None
+    };
     
-    // Get the blob
-    let blob = repo.find_blob(tree_entry.id()).map_err(|e| e.to_string())?;
+    Ok((base_id, head_id, merge_base_id))
+}
+
+fn comparison_diff(repo: &git2::Repository, old_id: git2::Oid, new_id: git2::Oid) -> Result<git2::Diff<'_>, String> {
+    let old_tree = repo.find_commit(old_id).and_then(|commit| commit.tree()).map_err(|e| e.to_string())?;
+    let new_tree = repo.find_commit(new_id).and_then(|commit| commit.tree()).map_err(|e| e.to_string())?;
     
-    // Check if file is binary
-    if blob.is_binary() {
-        return Err("Cannot display binary file content".to_string());
-    }
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.context_lines(3);
     
-    // Check file size limits for performance
-    const MAX_FILE_SIZE: usize = 1024 * 1024; // 1MB
+    let mut diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut diff_opts)).map_err(|e| e.to_string())?;
+    diff.find_similar(None).map_err(|e| e.to_string())?;
     
-    if blob.size() > MAX_FILE_SIZE {
-        return Err(format!("File too large to display ({}KB > 1MB)", blob.size() / 1024));
+    Ok(diff)
+}
+
+#[tauri::command]
+fn open_repo_dialog(app: tauri::AppHandle) {
+    let _ = app.emit("menu-open-repo", ());
+}
+
+#[tauri::command]
+fn global_search(sessions: State<'_, RepoSessions>, path: String, query: String, branch_name: Option<String>, max_commits: Option<u32>) -> Result<Vec<SearchResult>, String> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
     }
     
-    // Get file content
-    let content = String::from_utf8(blob.content().to_vec()).map_err(|e| format!("File is not valid UTF-8: {}", e))?;
+    let handle = sessions.open(&path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let repo = handle.lock();
     
-    Ok(content)
-}
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-  tauri::Builder::default()
-    .plugin(tauri_plugin_dialog::init())
-    .invoke_handler(tauri::generate_handler![get_git_branches, get_git_branches_from_path, get_git_remotes_from_path, get_commits_from_path, get_commit_changes, get_file_diff, open_repo_dialog, global_search, get_file_blame, get_commit_file_tree, get_file_content, open_file_in_editor, get_staged_changes, get_staged_file_diff, get_stashes, get_stash_diff, get_stash_file_diff])
-    .setup(|app| {
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
-      }
-      Ok(())
-    })
-    .on_menu_event(|app, event| {
-      match event.id().as_ref() {
-        "open_repo" => {
-          let _ = app.emit("menu-open-repo", ());
-        }
-        _ => {}
-      }
-    })
-    .menu(|app| {
-      #[cfg(target_os = "macos")]
-      {
-        use tauri::menu::*;
+    let query_lower = query.to_lowercase();
+    
+    // Determine which branch to search (default to current branch if not specified)
+    let target_branch = if let Some(branch) = branch_name {
+        branch
+    } else {
+        // Get current branch
+        let head = repo.head().map_err(|e| format!("Failed to get HEAD: {}", e))?;
+        let branch_name = head.shorthand().unwrap_or("HEAD").to_string();
+        branch_name
+    };
+    
+    // Find the branch and get commits
+    let branch_result = repo.find_branch(&target_branch, git2::BranchType::Local);
+    let branch = match branch_result {
+        Ok(b) => b,
+        Err(_) => {
+            // If branch not found, try to resolve HEAD
+            let head = repo.head().map_err(|e| format!("Failed to get HEAD: {}", e))?;
+            let commit = head.peel_to_commit().map_err(|e| format!("Failed to get commit: {}", e))?;
+            
+            // Search in recent commits from HEAD
+            let mut revwalk = repo.revwalk().map_err(|e| format!("Failed to create revwalk: {}", e))?;
+            revwalk.push(commit.id()).map_err(|e| format!("Failed to push commit: {}", e))?;
+            revwalk.set_sorting(git2::Sort::TIME).map_err(|e| format!("Failed to set sorting: {}", e))?;
+            
+            return search_commits_and_content(&repo, revwalk, &query_lower, max_commits);
+        }
+    };
+    
+    let commit = branch.get().peel_to_commit().map_err(|e| format!("Failed to get commit: {}", e))?;
+    let mut revwalk = repo.revwalk().map_err(|e| format!("Failed to create revwalk: {}", e))?;
+    revwalk.push(commit.id()).map_err(|e| format!("Failed to push commit: {}", e))?;
+    revwalk.set_sorting(git2::Sort::TIME).map_err(|e| format!("Failed to set sorting: {}", e))?;
+    
+    search_commits_and_content(&repo, revwalk, &query_lower, max_commits)
+}
+
+fn search_commits_and_content(repo: &git2::Repository, revwalk: git2::Revwalk, query: &str, max_commits: Option<u32>) -> Result<Vec<SearchResult>, String> {
+    let mut results = Vec::new();
+    let mut count = 0;
+    const MAX_RESULTS: usize = 50;
+    let max_commits_limit = max_commits.unwrap_or(100) as usize;
+    
+    for oid in revwalk {
+        if count >= max_commits_limit || results.len() >= MAX_RESULTS {
+            break;
+        }
         
-        let app_menu = SubmenuBuilder::new(app, "Git Viewer")
-          .about(None)
-          .separator()
-          .quit()
-          .build()?;
-          
-        let file_menu = SubmenuBuilder::new(app, "File")
-          .item(&MenuItemBuilder::with_id("open_repo", "Open Repository...")
-            .accelerator("Cmd+O")
-            .build(app)?)
-          .separator()
-          .close_window()
-          .build()?;
-          
-        MenuBuilder::new(app)
-          .item(&app_menu)
-          .item(&file_menu)
-          .build()
-      }
-      
-      #[cfg(not(target_os = "macos"))]
-      {
-        use tauri::menu::*;
+        let oid = oid.map_err(|e| format!("Failed to get OID: {}", e))?;
+        let commit = repo.find_commit(oid).map_err(|e| format!("Failed to find commit: {}", e))?;
         
-        let file_menu = SubmenuBuilder::new(app, "File")
-          .item(&MenuItemBuilder::with_id("open_repo", "Open Repository...")
-            .accelerator("Ctrl+O")
-            .build(app)?)
-          .separator()
-          .quit()
-          .build()?;
-          
-        MenuBuilder::new(app)
-          .item(&file_menu)
-          .build()
-      }
+        // Skip merge commits (commits with more than 1 parent)
+        if commit.parent_count() > 1 {
+            count += 1;
+            continue;
+        }
+        
+        let message = commit.message().unwrap_or("No message").to_string();
+        let author = commit.author();
+        let author_name = author.name().unwrap_or("Unknown").to_string();
+        let date = commit.time();
+        let date_str = format!("{}", chrono::DateTime::from_timestamp(date.seconds(), 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M:%S"));
+        
+        // Search in commit message
+        if message.to_lowercase().contains(query) {
+            results.push(SearchResult {
+                result_type: "commit".to_string(),
+                commit_id: oid.to_string(),
+                commit_message: message.clone(),
+                commit_author: author_name.clone(),
+                commit_date: date_str.clone(),
+                file_path: None,
+                content_preview: Some(message.lines().next().unwrap_or(&message).to_string()),
+                line_number: None,
+            });
+        }
+        
+        // Search in file names and content
+        if let Err(_) = search_commit_files_and_content(repo, &commit, query, &mut results, &author_name, &date_str) {
+            // Continue even if individual commit search fails
+        }
+        
+        count += 1;
+        
+        if results.len() >= MAX_RESULTS {
+            break;
+        }
+    }
+    
+    Ok(results)
+}
+
+fn search_commit_files_and_content(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+    query: &str,
+    results: &mut Vec<SearchResult>,
+    author_name: &str,
+    date_str: &str,
+) -> Result<(), String> {
+    let tree = commit.tree().map_err(|e| format!("Failed to get tree: {}", e))?;
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(commit.parent(0).map_err(|e| format!("Failed to get parent: {}", e))?.tree().map_err(|e| format!("Failed to get parent tree: {}", e))?)
+    } else {
+        None
+    };
+    
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.context_lines(2);
+    diff_opts.max_size(512 * 1024); // 512KB limit for content search
+    
+    let diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&tree),
+        Some(&mut diff_opts)
+    ).map_err(|e| format!("Failed to create diff: {}", e))?;
+    
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        if results.len() >= 50 { // Limit results
+            break;
+        }
+        
+        let file_path = delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str())
+            .unwrap_or("unknown");
+        
+        // Search in file names
+        if file_path.to_lowercase().contains(query) {
+            results.push(SearchResult {
+                result_type: "file".to_string(),
+                commit_id: commit.id().to_string(),
+                commit_message: commit.message().unwrap_or("No message").lines().next().unwrap_or("").to_string(),
+                commit_author: author_name.to_string(),
+                commit_date: date_str.to_string(),
+                file_path: Some(file_path.to_string()),
+                content_preview: Some(format!("File: {}", file_path)),
+                line_number: None,
+            });
+        }
+        
+        // Search in file content (only for text files)
+        if !delta.new_file().is_binary() && !delta.old_file().is_binary() {
+            if let Ok(Some(patch)) = git2::Patch::from_diff(&diff, delta_idx) {
+                for hunk_idx in 0..patch.num_hunks() {
+                    if let Ok((_, hunk_lines)) = patch.hunk(hunk_idx) {
+                        for line_idx in 0..hunk_lines {
+                            if let Ok(line) = patch.line_in_hunk(hunk_idx, line_idx) {
+                                let line_content = String::from_utf8_lossy(line.content());
+                                if line_content.to_lowercase().contains(query) {
+                                    let preview = line_content.trim_end_matches('\n').to_string();
+                                    let preview_truncated = if preview.len() > 100 {
+                                        format!("{}...", &preview[..97])
+                                    } else {
+                                        preview
+                                    };
+                                    
+                                    results.push(SearchResult {
+                                        result_type: "content".to_string(),
+                                        commit_id: commit.id().to_string(),
+                                        commit_message: commit.message().unwrap_or("No message").lines().next().unwrap_or("").to_string(),
+                                        commit_author: author_name.to_string(),
+                                        commit_date: date_str.to_string(),
+                                        file_path: Some(file_path.to_string()),
+                                        content_preview: Some(preview_truncated),
+                                        line_number: line.new_lineno().or(line.old_lineno()),
+                                    });
+                                    
+                                    // Limit content results per file
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn get_file_blame(sessions: State<'_, RepoSessions>, path: String, commit_id: String, file_path: String, working_tree: Option<bool>) -> Result<FileBlame, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    // Blame the checked-out file (including uncommitted edits) instead of the
+    // file as it was at `commit_id`
+    let working_tree = working_tree.unwrap_or(false);
+    
+    let content = load_blame_content(&repo, &commit_id, &file_path, working_tree)?;
+    
+    // Check file size limits for performance
+    const MAX_BLAME_SIZE: usize = 1024 * 1024; // 1MB
+    const MAX_BLAME_LINES: usize = 3000; // 3000 lines
+    
+    if content.len() > MAX_BLAME_SIZE {
+        return Err(format!("File too large for blame view ({}KB > 1MB)", content.len() / 1024));
+    }
+    
+    let lines: Vec<&str> = content.lines().collect();
+    
+    if lines.len() > MAX_BLAME_LINES {
+        return Err(format!("File has too many lines for blame view ({} > {})", lines.len(), MAX_BLAME_LINES));
+    }
+    
+    // A file that is not in HEAD yet (new, untracked or only staged) has no
+    // history to blame, every line is uncommitted
+    if working_tree {
+        let in_head = repo.head()
+            .and_then(|head| head.peel_to_tree())
+            .is_ok_and(|tree| tree.get_path(Path::new(&file_path)).is_ok());
+        if !in_head {
+            let blame_lines = lines.iter().enumerate()
+                .map(|(line_idx, line)| {
+                    let line_number = line_idx as u32 + 1;
+                    uncommitted_blame_info(line_number, line, file_path.clone(), line_number)
+                })
+                .collect();
+            return Ok(FileBlame {
+                path: file_path,
+                blame_lines,
+            });
+        }
+    }
+    
+    // Create blame options
+    let mut blame_options = git2::BlameOptions::new();
+    blame_options.track_copies_same_commit_moves(true);
+    blame_options.track_copies_same_commit_copies(true);
+    
+    // Get blame for the file. Without a newest commit libgit2 blames HEAD,
+    // which would not line up with the content of an older revision.
+    if !working_tree {
+        let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+        blame_options.newest_commit(oid);
+    }
+    let committed_blame = repo.blame_file(Path::new(&file_path), Some(&mut blame_options)).map_err(|e| e.to_string())?;
+    
+    // In working tree mode, layer the uncommitted content on top of the HEAD blame
+    let buffer_blame = if working_tree {
+        Some(committed_blame.blame_buffer(content.as_bytes()).map_err(|e| e.to_string())?)
+    } else {
+        None
+    };
+    let blame = buffer_blame.as_ref().unwrap_or(&committed_blame);
+    
+    let blame_lines = collect_blame_lines(&repo, blame, &lines, 1, &commit_id, &file_path)?;
+    
+    Ok(FileBlame {
+        path: file_path,
+        blame_lines,
     })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use std::process::Command;
-    use tempfile::TempDir;
+/// Blames only lines `start_line..=end_line` (1-based) of the file at
+/// `commit_id`. Not subject to the size limits of `get_file_blame`, so the
+/// frontend can blame huge files one visible range at a time.
+#[tauri::command]
+fn get_file_blame_range(sessions: State<'_, RepoSessions>, path: String, commit_id: String, file_path: String, start_line: u32, end_line: u32) -> Result<FileBlame, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let content = load_blame_content(&repo, &commit_id, &file_path, false)?;
+    let lines: Vec<&str> = content.lines().collect();
+    
+    if start_line == 0 || start_line > end_line {
+        return Err(format!("Invalid line range {}-{}", start_line, end_line));
+    }
+    
+    let start = (start_line as usize - 1).min(lines.len());
+    let end = (end_line as usize).min(lines.len());
+    let blame_lines = blame_line_range(&repo, &commit_id, &file_path, &lines[start..end], start + 1)?;
+    
+    Ok(FileBlame {
+        path: file_path,
+        blame_lines,
+    })
+}
+
+/// Blame streams that are still running, each with its own cancellation
+/// flag so that windows can start and cancel streams independently.
+#[derive(Default)]
+pub struct BlameStreams {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<u64, Arc<AtomicBool>>>,
+}
+
+impl BlameStreams {
+    fn start(&self) -> (u64, Arc<AtomicBool>) {
+        let stream_id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).insert(stream_id, cancelled.clone());
+        (stream_id, cancelled)
+    }
+    
+    fn cancel(&self, stream_id: u64) {
+        if let Some(cancelled) = self.running.lock().unwrap_or_else(|e| e.into_inner()).get(&stream_id) {
+            cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+    
+    fn finish(&self, stream_id: u64) {
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).remove(&stream_id);
+    }
+}
+
+const DEFAULT_BLAME_CHUNK_SIZE: usize = 500;
+
+/// Starts blaming the file at `commit_id` in the background and emits the
+/// result as `blame-chunk` events of `chunk_size` lines each, so files of
+/// any size can be shown progressively.
+#[tauri::command(async)]
+fn stream_file_blame(app: tauri::AppHandle, path: String, commit_id: String, file_path: String, chunk_size: Option<u32>) -> Result<BlameStream, String> {
+    let content = {
+        let sessions = app.state::<RepoSessions>();
+        let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+        let repo = handle.lock();
+        load_blame_content(&repo, &commit_id, &file_path, false)?
+    };
+    let total_lines = content.lines().count() as u32;
+    let chunk_size = chunk_size.map(|size| size.max(1) as usize).unwrap_or(DEFAULT_BLAME_CHUNK_SIZE);
+    // Blame on a handle of its own, so other commands don't wait for it
+    let repo = git2::Repository::open(&path).map_err(|e| e.to_string())?;
+    let (stream_id, cancelled) = app.state::<BlameStreams>().start();
+    
+    std::thread::spawn(move || {
+        blame_in_chunks(&repo, &cancelled, stream_id, &commit_id, &file_path, &content, chunk_size, |chunk| {
+            let _ = app.emit("blame-chunk", chunk);
+        });
+        app.state::<BlameStreams>().finish(stream_id);
+    });
+    
+    Ok(BlameStream {
+        stream_id,
+        total_lines,
+    })
+}
+
+#[tauri::command]
+fn cancel_blame_stream(streams: State<'_, BlameStreams>, stream_id: u64) {
+    streams.cancel(stream_id);
+}
+
+/// Blames the whole file once, then converts and emits the result a chunk at
+/// a time, checking `cancelled` between chunks.
+#[allow(clippy::too_many_arguments)]
+fn blame_in_chunks<F: FnMut(BlameChunk)>(
+    repo: &git2::Repository,
+    cancelled: &AtomicBool,
+    stream_id: u64,
+    commit_id: &str,
+    file_path: &str,
+    content: &str,
+    chunk_size: usize,
+    mut emit: F,
+) {
+    let lines: Vec<&str> = content.lines().collect();
+    let total_lines = lines.len() as u32;
+    let chunk = |start: usize, blame_lines: Vec<BlameInfo>, done: bool, error: Option<String>| BlameChunk {
+        stream_id,
+        path: file_path.to_string(),
+        start_line: (start + 1) as u32,
+        total_lines,
+        blame_lines,
+        done,
+        error,
+    };
+    
+    if cancelled.load(Ordering::SeqCst) {
+        return;
+    }
+    
+    let blame = git2::Oid::from_str(commit_id).map_err(|e| e.to_string()).and_then(|oid| {
+        let mut blame_options = git2::BlameOptions::new();
+        blame_options.track_copies_same_commit_moves(true);
+        blame_options.track_copies_same_commit_copies(true);
+        blame_options.newest_commit(oid);
+        repo.blame_file(Path::new(file_path), Some(&mut blame_options)).map_err(|e| e.to_string())
+    });
+    let blame = match blame {
+        Ok(blame) => blame,
+        Err(e) => {
+            emit(chunk(0, Vec::new(), true, Some(e)));
+            return;
+        }
+    };
+    
+    let mut start = 0;
+    loop {
+        if cancelled.load(Ordering::SeqCst) {
+            return;
+        }
+        
+        let end = (start + chunk_size).min(lines.len());
+        let result = collect_blame_lines(repo, &blame, &lines[start..end], start + 1, commit_id, file_path);
+        
+        let done = end >= lines.len() || result.is_err();
+        match result {
+            Ok(blame_lines) => emit(chunk(start, blame_lines, done, None)),
+            Err(e) => emit(chunk(start, Vec::new(), done, Some(e))),
+        }
+        
+        if done {
+            return;
+        }
+        start = end;
+    }
+}
+
+/// Looks past the commit that last touched a line: finds the commit that
+/// introduced line `line_number` of `file_path` at revision `commit_id`, maps
+/// the line into that commit's first parent and blames it there. Calling this
+/// again with the returned revision, path and line number walks further back.
+#[tauri::command]
+fn blame_line_at_parent(sessions: State<'_, RepoSessions>, path: String, commit_id: String, file_path: String, line_number: u32) -> Result<ParentBlame, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    if line_number == 0 {
+        return Err("Line numbers start at 1".to_string());
+    }
+    let line = line_number as usize;
+    
+    // Find where the line came from
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let mut blame_options = git2::BlameOptions::new();
+    blame_options.track_copies_same_commit_moves(true);
+    blame_options.track_copies_same_commit_copies(true);
+    blame_options.newest_commit(oid);
+    blame_options.min_line(line);
+    blame_options.max_line(line);
+    
+    let blame = repo.blame_file(Path::new(&file_path), Some(&mut blame_options)).map_err(|e| e.to_string())?;
+    let hunk = blame.get_line(line).ok_or_else(|| format!("Line {} not found in '{}'", line_number, file_path))?;
+    
+    let origin_oid = hunk.orig_commit_id();
+    let origin_path = hunk.path()
+        .and_then(|p| p.to_str())
+        .unwrap_or(&file_path)
+        .to_string();
+    let origin_line = hunk.orig_start_line() + (line - hunk.final_start_line());
+    
+    let origin_commit = repo.find_commit(origin_oid).map_err(|e| e.to_string())?;
+    if origin_commit.parent_count() == 0 {
+        return Err(format!("Line {} was introduced in the root commit {}", line_number, origin_oid));
+    }
+    let parent = origin_commit.parent(0).map_err(|e| e.to_string())?;
+    
+    // Follow the file and the line through the origin commit's diff
+    let parent_tree = parent.tree().map_err(|e| e.to_string())?;
+    let origin_tree = origin_commit.tree().map_err(|e| e.to_string())?;
+    let mut diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&origin_tree), None).map_err(|e| e.to_string())?;
+    diff.find_similar(None).map_err(|e| e.to_string())?;
+    
+    let delta_idx = diff.deltas()
+        .position(|delta| delta.new_file().path().and_then(|p| p.to_str()) == Some(origin_path.as_str()))
+        .ok_or_else(|| format!("File '{}' not changed in commit {}", origin_path, origin_oid))?;
+    let delta = diff.get_delta(delta_idx).ok_or("Failed to get diff delta")?;
+    
+    if delta.status() == git2::Delta::Added {
+        return Err(format!("File '{}' was added in commit {}", origin_path, origin_oid));
+    }
+    let parent_path = delta.old_file().path()
+        .and_then(|p| p.to_str())
+        .unwrap_or(&origin_path)
+        .to_string();
+    
+    let patch = git2::Patch::from_diff(&diff, delta_idx)
+        .map_err(|e| format!("Failed to create patch: {}", e))?
+        .ok_or("Cannot follow lines through a binary file")?;
+    let parent_line = map_line_to_parent(&patch, origin_line as u32)?;
+    
+    // Blame the mapped line at the parent
+    let parent_id = parent.id().to_string();
+    let content = load_blame_content(&repo, &parent_id, &parent_path, false)?;
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.is_empty() {
+        return Err(format!("File '{}' is empty in commit {}", parent_path, parent_id));
+    }
+    let parent_line = (parent_line as usize).clamp(1, lines.len());
+    
+    let blame = blame_line_range(&repo, &parent_id, &parent_path, &lines[parent_line - 1..parent_line], parent_line)?
+        .pop()
+        .ok_or("Failed to blame line at parent")?;
+    
+    Ok(ParentBlame {
+        skipped_commit_id: origin_oid.to_string(),
+        revision: parent_id,
+        path: parent_path,
+        blame,
+    })
+}
+
+/// Maps a line of the new side of `patch` to the closest line on the old
+/// side. Added lines that replace deleted ones map to the deleted line at the
+/// same position, so a reformatted line maps to its previous version.
+fn map_line_to_parent(patch: &git2::Patch, new_line: u32) -> Result<u32, String> {
+    // Difference between old and new line numbers after the hunks seen so far
+    let mut offset: i64 = 0;
+    
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| format!("Failed to get hunk: {}", e))?;
+        
+        if new_line < hunk.new_start() {
+            break;
+        }
+        if new_line >= hunk.new_start() + hunk.new_lines() {
+            offset = (hunk.old_start() + hunk.old_lines()) as i64 - (hunk.new_start() + hunk.new_lines()) as i64;
+            continue;
+        }
+        
+        // The line is inside this hunk
+        let mut last_old_line = hunk.old_start().saturating_sub(1);
+        let mut deleted_run: Vec<u32> = Vec::new();
+        let mut run_position = 0;
+        
+        for line_idx in 0..hunk_lines {
+            let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| format!("Failed to get line: {}", e))?;
+            match line.origin() {
+                '-' => {
+                    if run_position > 0 {
+                        deleted_run.clear();
+                        run_position = 0;
+                    }
+                    if let Some(old) = line.old_lineno() {
+                        deleted_run.push(old);
+                        last_old_line = old;
+                    }
+                }
+                '+' => {
+                    if line.new_lineno() == Some(new_line) {
+                        return Ok(deleted_run.get(run_position).copied().unwrap_or(last_old_line).max(1));
+                    }
+                    run_position += 1;
+                }
+                _ => {
+                    if line.new_lineno() == Some(new_line) {
+                        return Ok(line.old_lineno().unwrap_or(last_old_line));
+                    }
+                    if let Some(old) = line.old_lineno() {
+                        last_old_line = old;
+                    }
+                    deleted_run.clear();
+                    run_position = 0;
+                }
+            }
+        }
+        return Ok(last_old_line.max(1));
+    }
+    
+    Ok((new_line as i64 + offset).max(1) as u32)
+}
+
+/// Reads the file to blame, either from `commit_id` or from the working tree.
+fn load_blame_content(repo: &git2::Repository, commit_id: &str, file_path: &str, working_tree: bool) -> Result<String, String> {
+    let (bytes, is_binary) = if working_tree {
+        let workdir = repo.workdir().ok_or("Cannot blame the working tree of a bare repository")?;
+        let bytes = std::fs::read(workdir.join(file_path)).map_err(|e| {
+            format!("File '{}' not found in the working tree: {}", file_path, e)
+        })?;
+        // Same heuristic as git: a NUL byte near the start means binary
+        let is_binary = bytes.iter().take(8000).any(|&b| b == 0);
+        (bytes, is_binary)
+    } else {
+        // Get the commit
+        let oid = git2::Oid::from_str(commit_id).map_err(|e| e.to_string())?;
+        let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+        
+        // Get the tree
+        let tree = commit.tree().map_err(|e| e.to_string())?;
+        
+        // Find the file in the tree
+        let tree_entry = tree.get_path(Path::new(file_path)).map_err(|e| {
+            format!("File '{}' not found in commit '{}': {}", file_path, commit_id, e)
+        })?;
+        
+        // Get the blob
+        let blob = repo.find_blob(tree_entry.id()).map_err(|e| e.to_string())?;
+        (blob.content().to_vec(), blob.is_binary())
+    };
+    
+    // Check if file is binary
+    if is_binary {
+        return Err("Cannot show blame for binary files".to_string());
+    }
+    
+    String::from_utf8(bytes).map_err(|e| format!("File is not valid UTF-8: {}", e))
+}
+
+/// Blames `lines`, which start at 1-based line `first_line` of the file at
+/// `commit_id`. libgit2 only tracks the requested range through history.
+fn blame_line_range(repo: &git2::Repository, commit_id: &str, file_path: &str, lines: &[&str], first_line: usize) -> Result<Vec<BlameInfo>, String> {
+    if lines.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    let oid = git2::Oid::from_str(commit_id).map_err(|e| e.to_string())?;
+    
+    let mut blame_options = git2::BlameOptions::new();
+    blame_options.track_copies_same_commit_moves(true);
+    blame_options.track_copies_same_commit_copies(true);
+    blame_options.newest_commit(oid);
+    blame_options.min_line(first_line);
+    blame_options.max_line(first_line + lines.len() - 1);
+    
+    let blame = repo.blame_file(Path::new(file_path), Some(&mut blame_options)).map_err(|e| e.to_string())?;
+    
+    collect_blame_lines(repo, &blame, lines, first_line, commit_id, file_path)
+}
+
+fn uncommitted_blame_info(line_number: u32, content: &str, original_path: String, original_line_number: u32) -> BlameInfo {
+    let commit_id = git2::Oid::zero().to_string();
+    BlameInfo {
+        commit_short_id: commit_id[..8].to_string(),
+        commit_id,
+        author: "Not Committed Yet".to_string(),
+        date: "Unknown".to_string(),
+        committer: "Not Committed Yet".to_string(),
+        committer_date: "Unknown".to_string(),
+        line_number,
+        content: content.to_string(),
+        commit_message: "Uncommitted changes".to_string(),
+        original_path,
+        original_line_number,
+        is_boundary: false,
+    }
+}
+
+fn collect_blame_lines(repo: &git2::Repository, blame: &git2::Blame, lines: &[&str], first_line: usize, commit_id: &str, file_path: &str) -> Result<Vec<BlameInfo>, String> {
+    let mut blame_lines = Vec::new();
+    
+    for (line_idx, line_content) in lines.iter().enumerate() {
+        let line_number = (first_line + line_idx) as u32;
+        
+        // Get blame info for this line
+        if let Some(hunk) = blame.get_line(line_number as usize) {
+            let commit_oid = hunk.final_commit_id();
+            
+            // Where the line sits in the commit it came from, which differs
+            // from the blamed file when code was moved or copied
+            let original_path = hunk.path()
+                .and_then(|p| p.to_str())
+                .unwrap_or(file_path)
+                .to_string();
+            let original_line_number = (hunk.orig_start_line() + (line_number as usize - hunk.final_start_line())) as u32;
+            
+            // Lines changed in the working tree are not attributed to any commit
+            if commit_oid.is_zero() {
+                blame_lines.push(uncommitted_blame_info(line_number, line_content, original_path, original_line_number));
+                continue;
+            }
+            
+            let blame_commit = repo.find_commit(commit_oid).map_err(|e| e.to_string())?;
+            
+            // Get author and committer info
+            let author = blame_commit.author();
+            let author_name = author.name().unwrap_or("Unknown").to_string();
+            let committer = blame_commit.committer();
+            let committer_name = committer.name().unwrap_or("Unknown").to_string();
+            
+            // Format dates
+            let format_date = |time: git2::Time| {
+                chrono::DateTime::from_timestamp(time.seconds(), 0)
+                    .map(|dt| dt.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "Unknown".to_string())
+            };
+            
+            // Get commit message (first line only)
+            let commit_message = blame_commit.message()
+                .unwrap_or("No message")
+                .lines()
+                .next()
+                .unwrap_or("")
+                .to_string();
+            
+            blame_lines.push(BlameInfo {
+                commit_id: commit_oid.to_string(),
+                commit_short_id: commit_oid.to_string()[..8].to_string(),
+                author: author_name,
+                date: format_date(author.when()),
+                committer: committer_name,
+                committer_date: format_date(committer.when()),
+                line_number,
+                content: line_content.to_string(),
+                commit_message,
+                original_path,
+                original_line_number,
+                is_boundary: hunk.is_boundary(),
+            });
+        } else {
+            // Fallback for lines without blame info
+            blame_lines.push(BlameInfo {
+                commit_id: commit_id.to_string(),
+                commit_short_id: commit_id.chars().take(8).collect(),
+                author: "Unknown".to_string(),
+                date: "Unknown".to_string(),
+                committer: "Unknown".to_string(),
+                committer_date: "Unknown".to_string(),
+                line_number,
+                content: line_content.to_string(),
+                commit_message: "Unknown".to_string(),
+                original_path: file_path.to_string(),
+                original_line_number: line_number,
+                is_boundary: false,
+            });
+        }
+    }
+    
+    Ok(blame_lines)
+}
+
+#[tauri::command]
+fn get_commit_file_tree(sessions: State<'_, RepoSessions>, path: String, commit_id: String) -> Result<Vec<FileTreeItem>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+    
+    fn get_file_type(name: &str) -> String {
+        let extension = name.split('.').last().unwrap_or("").to_lowercase();
+        match extension.as_str() {
+            "js" | "jsx" => "javascript",
+            "ts" | "tsx" => "typescript", 
+            "py" => "python",
+            "rs" => "rust",
+            "java" => "java",
+            "go" => "go",
+            "c" | "h" => "c",
+            "cpp" | "cc" | "cxx" | "hpp" => "cpp",
+            "css" | "scss" | "sass" => "css",
+            "html" | "htm" => "html",
+            "json" => "json",
+            "yaml" | "yml" => "yaml",
+            "md" | "markdown" => "markdown",
+            "sh" | "bash" | "zsh" => "shell",
+            "sql" => "sql",
+            "xml" => "xml",
+            "toml" => "toml",
+            "ini" | "cfg" | "conf" => "config",
+            "dockerfile" => "docker",
+            "gitignore" => "git",
+            "txt" => "text",
+            _ => "file"
+        }.to_string()
+    }
+    
+    fn build_tree_recursive(
+        repo: &git2::Repository,
+        tree: &git2::Tree,
+        base_path: &str,
+    ) -> Result<Vec<FileTreeItem>, String> {
+        let mut items = Vec::new();
+        
+        for entry in tree.iter() {
+            let name = entry.name().unwrap_or("unknown").to_string();
+            let current_path = if base_path.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", base_path, name)
+            };
+            
+            let object = entry.to_object(repo).map_err(|e| e.to_string())?;
+            
+            match object.kind() {
+                Some(git2::ObjectType::Tree) => {
+                    let subtree = object.as_tree().unwrap();
+                    let children = build_tree_recursive(repo, subtree, &current_path)?;
+                    
+                    items.push(FileTreeItem {
+                        name,
+                        path: current_path,
+                        is_directory: true,
+                        children: Some(children),
+                        size: None,
+                        file_type: "folder".to_string(),
+                    });
+                }
+                Some(git2::ObjectType::Blob) => {
+                    let blob = object.as_blob().unwrap();
+                    let file_type = get_file_type(&name);
+                    
+                    items.push(FileTreeItem {
+                        name: name.clone(),
+                        path: current_path,
+                        is_directory: false,
+                        children: None,
+                        size: Some(blob.size() as u64),
+                        file_type,
+                    });
+                }
+                _ => {}
+            }
+        }
+        
+        // Sort items: directories first, then files, both alphabetically
+        items.sort_by(|a, b| {
+            match (a.is_directory, b.is_directory) {
+                (true, false) => std::cmp::Ordering::Less,
+                (false, true) => std::cmp::Ordering::Greater,
+                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            }
+        });
+        
+        Ok(items)
+    }
+    
+    build_tree_recursive(&repo, &tree, "")
+}
+
+#[tauri::command]
+fn open_file_in_editor(sessions: State<'_, RepoSessions>, repo_path: String, commit_id: String, file_path: String) -> Result<(), String> {
+    use std::process::Command;
+    use std::fs;
+    use std::io::Write;
+    
+    let handle = sessions.open(&repo_path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    // Get the commit
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+    
+    // Find the file in the tree
+    let tree_entry = tree.get_path(Path::new(&file_path)).map_err(|e| {
+        format!("File '{}' not found in commit '{}': {}", file_path, commit_id, e)
+    })?;
+    
+    // Get the blob
+    let blob = repo.find_blob(tree_entry.id()).map_err(|e| e.to_string())?;
+    
+    // Check if file is binary
+    if blob.is_binary() {
+        return Err("Cannot open binary files in text editor".to_string());
+    }
+    
+    // Get file content
+    let content = String::from_utf8(blob.content().to_vec()).map_err(|e| format!("File is not valid UTF-8: {}", e))?;
+    
+    // Create temporary file
+    let temp_dir = std::env::temp_dir();
+    let file_name = Path::new(&file_path).file_name().unwrap_or_else(|| std::ffi::OsStr::new("temp_file"));
+    let temp_file_path = temp_dir.join(format!("git_viewer_{}_{}", commit_id[..8].to_string(), file_name.to_string_lossy()));
+    
+    // Write content to temporary file
+    let mut temp_file = fs::File::create(&temp_file_path).map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    temp_file.write_all(content.as_bytes()).map_err(|e| format!("Failed to write to temporary file: {}", e))?;
+    
+    let temp_file_str = temp_file_path.to_str().ok_or("Invalid temporary file path")?;
+    
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg("-a")
+            .arg("TextEdit")
+            .arg(temp_file_str)
+            .spawn()
+            .map_err(|e| format!("Failed to open file in TextEdit: {}", e))?;
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("notepad")
+            .arg(temp_file_str)
+            .spawn()
+            .map_err(|e| format!("Failed to open file in Notepad: {}", e))?;
+    }
+    
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("gedit")
+            .arg(temp_file_str)
+            .spawn()
+            .map_err(|e| format!("Failed to open file in gedit: {}", e))?;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn get_staged_changes(sessions: State<'_, RepoSessions>, path: String) -> Result<Vec<StagedChange>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let mut staged_changes = Vec::new();
+    
+    // Use Git's status functionality to get staged files
+    let mut status_options = git2::StatusOptions::new();
+    status_options.include_untracked(false);
+    status_options.include_ignored(false);
+    
+    let statuses = repo.statuses(Some(&mut status_options)).map_err(|e| e.to_string())?;
+    
+    // Line counts come from the HEAD-vs-index diff, keyed by path
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree().map_err(|e| e.to_string())?),
+        Err(_) => None, // Repository has no commits yet
+    };
+    let index = repo.index().map_err(|e| e.to_string())?;
+    let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None).map_err(|e| e.to_string())?;
+    
+    let mut line_stats = HashMap::new();
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        if let Some(delta_path) = delta.new_file().path().or_else(|| delta.old_file().path()).and_then(|p| p.to_str()) {
+            line_stats.insert(delta_path.to_string(), delta_line_stats(&diff, delta_idx)?);
+        }
+    }
+    
+    for status_entry in statuses.iter() {
+        let file_path = status_entry.path().unwrap_or("unknown");
+        let status_flags = status_entry.status();
+        
+        // Check if the file is staged (in index)
+        let status = if status_flags.contains(git2::Status::INDEX_NEW) {
+            "added"
+        } else if status_flags.contains(git2::Status::INDEX_MODIFIED) {
+            "modified"
+        } else if status_flags.contains(git2::Status::INDEX_DELETED) {
+            "deleted"
+        } else if status_flags.contains(git2::Status::INDEX_RENAMED) {
+            "renamed" // TODO: Get the old path for renames
+        } else if status_flags.contains(git2::Status::INDEX_TYPECHANGE) {
+            "modified"
+        } else {
+            continue;
+        };
+        
+        let (additions, deletions) = line_stats.get(file_path).copied().unwrap_or((0, 0));
+        
+        staged_changes.push(StagedChange {
+            path: file_path.to_string(),
+            status: status.to_string(),
+            old_path: None,
+            additions,
+            deletions,
+        });
+    }
+    
+    Ok(staged_changes)
+}
+
+#[tauri::command]
+fn get_staged_file_diff(sessions: State<'_, RepoSessions>, path: String, file_path: String) -> Result<FileDiff, String> {
+    let handle = sessions.open(&path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let repo = handle.lock();
+    
+    // Get the index (staging area)
+    let index = repo.index().map_err(|e| format!("Failed to get index: {}", e))?;
+    
+    // Get HEAD tree for comparison (if it exists)
+    let head_tree = match repo.head() {
+        Ok(head) => {
+            let head_commit = head.peel_to_commit().map_err(|e| format!("Failed to get HEAD commit: {}", e))?;
+            Some(head_commit.tree().map_err(|e| format!("Failed to get HEAD tree: {}", e))?)
+        }
+        Err(_) => None, // Repository has no commits yet
+    };
+    
+    // Create diff options
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.context_lines(3);
+    diff_opts.max_size(1024 * 1024); // 1MB limit
+    diff_opts.pathspec(file_path.clone());
+    
+    // Create diff between HEAD and index (staged changes)
+    let diff = repo.diff_tree_to_index(
+        head_tree.as_ref(),
+        Some(&index),
+        Some(&mut diff_opts)
+    ).map_err(|e| format!("Failed to create diff: {}", e))?;
+    
+    // Find the specific file in the diff
+    let mut file_found = false;
+    let mut file_status = "unknown";
+    let mut is_binary = false;
+    
+    // First pass: find if the file exists in this diff
+    for (_delta_idx, delta) in diff.deltas().enumerate() {
+        let delta_path = delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str())
+            .unwrap_or("unknown");
+        
+        if delta_path == file_path {
+            file_found = true;
+            file_status = match delta.status() {
+                git2::Delta::Added => "added",
+                git2::Delta::Deleted => "deleted", 
+                git2::Delta::Modified => "modified",
+                git2::Delta::Renamed => "renamed",
+                git2::Delta::Copied => "copied",
+                _ => "unknown",
+            };
+            is_binary = delta.new_file().is_binary() || delta.old_file().is_binary();
+            break;
+        }
+    }
+    
+    if !file_found {
+        return Err(format!("File '{}' not found in staged changes", file_path));
+    }
+    
+    if is_binary {
+        return Ok(FileDiff {
+            path: file_path,
+            status: file_status.to_string(),
+            old_content: None,
+            new_content: None,
+            diff_lines: Vec::new(),
+            is_binary: true,
+        });
+    }
+    
+    // Generate patch for text files
+    let mut patch_lines = Vec::new();
+    
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        let delta_path = delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str())
+            .unwrap_or("unknown");
+        
+        if delta_path == file_path {
+            let patch = git2::Patch::from_diff(&diff, delta_idx).map_err(|e| format!("Failed to create patch: {}", e))?;
+            
+            if let Some(patch) = patch {
+                for hunk_idx in 0..patch.num_hunks() {
+                    let (_hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| format!("Failed to get hunk: {}", e))?;
+                    
+                    for line_idx in 0..hunk_lines {
+                        let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| format!("Failed to get line: {}", e))?;
+                        
+                        let line_content = String::from_utf8_lossy(line.content()).trim_end_matches('\n').to_string();
+                        let line_type = match line.origin() {
+                            '+' => "addition",
+                            '-' => "deletion", 
+                            ' ' => "context",
+                            _ => "context",
+                        };
+                        
+                        patch_lines.push(DiffLine {
+                            line_type: line_type.to_string(),
+                            content: line_content,
+                            old_line_number: line.old_lineno(),
+                            new_line_number: line.new_lineno(),
+                            hunk_index: hunk_idx as u32,
+                        });
+                    }
+                }
+            }
+            break;
+        }
+    }
+    
+    Ok(FileDiff {
+        path: file_path,
+        status: file_status.to_string(),
+        old_content: None,
+        new_content: None,
+        diff_lines: patch_lines,
+        is_binary: false,
+    })
+}
+
+/// Index-vs-workdir changes: unstaged modifications, untracked files and
+/// unresolved conflicts. Together with `get_staged_changes` this is the full
+/// `git status` of the checkout.
+#[tauri::command]
+fn get_working_tree_changes(sessions: State<'_, RepoSessions>, path: String) -> Result<Vec<WorkingTreeChange>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let mut status_options = git2::StatusOptions::new();
+    status_options.include_untracked(true);
+    status_options.recurse_untracked_dirs(true);
+    status_options.include_ignored(false);
+    status_options.renames_index_to_workdir(true);
+    
+    let statuses = repo.statuses(Some(&mut status_options)).map_err(|e| e.to_string())?;
+    
+    // Line counts come from the index-vs-workdir diff, keyed by path
+    let mut diff_opts = working_tree_diff_options();
+    let diff = repo.diff_index_to_workdir(None, Some(&mut diff_opts)).map_err(|e| e.to_string())?;
+    
+    let mut line_stats = HashMap::new();
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        if let Some(delta_path) = delta.new_file().path().or_else(|| delta.old_file().path()).and_then(|p| p.to_str()) {
+            line_stats.insert(delta_path.to_string(), delta_line_stats(&diff, delta_idx)?);
+        }
+    }
+    
+    let mut changes = Vec::new();
+    
+    for status_entry in statuses.iter() {
+        let status_flags = status_entry.status();
+        
+        let status = if status_flags.contains(git2::Status::CONFLICTED) {
+            "conflicted"
+        } else if status_flags.contains(git2::Status::WT_NEW) {
+            "untracked"
+        } else if status_flags.contains(git2::Status::WT_MODIFIED) || status_flags.contains(git2::Status::WT_TYPECHANGE) {
+            "modified"
+        } else if status_flags.contains(git2::Status::WT_DELETED) {
+            "deleted"
+        } else if status_flags.contains(git2::Status::WT_RENAMED) {
+            "renamed"
+        } else {
+            continue;
+        };
+        
+        // For renames the entry path is the old one, the new path is on the workdir side
+        let (file_path, old_path) = match status_entry.index_to_workdir() {
+            Some(delta) if status == "renamed" => (
+                delta.new_file().path().and_then(|p| p.to_str()).unwrap_or("unknown").to_string(),
+                delta.old_file().path().and_then(|p| p.to_str()).map(|p| p.to_string()),
+            ),
+            _ => (status_entry.path().unwrap_or("unknown").to_string(), None),
+        };
+        
+        let (additions, deletions) = line_stats.get(&file_path).copied().unwrap_or((0, 0));
+        
+        changes.push(WorkingTreeChange {
+            path: file_path,
+            status: status.to_string(),
+            old_path,
+            additions,
+            deletions,
+        });
+    }
+    
+    Ok(changes)
+}
+
+/// Old paths of the files renamed in the working tree, keyed by their new
+/// path, as `get_working_tree_changes` reports them.
+fn working_tree_renames(repo: &git2::Repository) -> Result<HashMap<String, String>, String> {
+    let mut status_options = git2::StatusOptions::new();
+    status_options.include_untracked(true);
+    status_options.recurse_untracked_dirs(true);
+    status_options.renames_index_to_workdir(true);
+    
+    let statuses = repo.statuses(Some(&mut status_options)).map_err(|e| e.to_string())?;
+    
+    Ok(statuses.iter()
+        .filter(|entry| entry.status().contains(git2::Status::WT_RENAMED) && !entry.status().contains(git2::Status::CONFLICTED))
+        .filter_map(|entry| {
+            let delta = entry.index_to_workdir()?;
+            let new_path = delta.new_file().path()?.to_str()?.to_string();
+            let old_path = delta.old_file().path()?.to_str()?.to_string();
+            Some((new_path, old_path))
+        })
+        .collect())
+}
+
+#[tauri::command]
+fn get_working_tree_file_diff(sessions: State<'_, RepoSessions>, path: String, file_path: String) -> Result<FileDiff, String> {
+    let handle = sessions.open(&path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let repo = handle.lock();
+    
+    let mut diff_opts = working_tree_diff_options();
+    diff_opts.context_lines(3);
+    diff_opts.max_size(1024 * 1024); // 1MB limit
+    diff_opts.pathspec(file_path.clone());
+    
+    let index = repo.index().map_err(|e| format!("Failed to get index: {}", e))?;
+    let is_conflicted = index.conflicts()
+        .map_err(|e| format!("Failed to read conflicts: {}", e))?
+        .filter_map(|conflict| conflict.ok())
+        .any(|conflict| {
+            [conflict.our, conflict.their, conflict.ancestor].iter().flatten()
+                .any(|entry| entry.path == file_path.as_bytes())
+        });
+    
+    // A conflicted file has no single index entry to compare against, so show
+    // the working tree file (with its conflict markers) against HEAD instead
+    let diff = if is_conflicted {
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree().map_err(|e| format!("Failed to get HEAD tree: {}", e))?),
+            Err(_) => None,
+        };
+        repo.diff_tree_to_workdir(head_tree.as_ref(), Some(&mut diff_opts))
+    } else {
+        repo.diff_index_to_workdir(Some(&index), Some(&mut diff_opts))
+    }.map_err(|e| format!("Failed to create diff: {}", e))?;
+    
+    let mut file_diff = build_file_diff(&diff, file_path, "working tree changes")?;
+    if is_conflicted {
+        file_diff.status = "conflicted".to_string();
+    }
+    
+    Ok(file_diff)
+}
+
+fn working_tree_diff_options() -> git2::DiffOptions {
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.include_untracked(true);
+    diff_opts.recurse_untracked_dirs(true);
+    diff_opts.show_untracked_content(true);
+    diff_opts
+}
+
+/// Builds the `FileDiff` for `file_path` out of `diff`. `location` names what
+/// was diffed, for the not-found error.
+fn build_file_diff(diff: &git2::Diff, file_path: String, location: &str) -> Result<FileDiff, String> {
+    let delta_idx = diff.deltas().position(|delta| {
+        delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str()) == Some(file_path.as_str())
+    }).ok_or_else(|| format!("File '{}' not found in {}", file_path, location))?;
+    
+    let delta = diff.get_delta(delta_idx).ok_or("Failed to get diff delta")?;
+    let status = delta_status(delta.status()).to_string();
+    
+    if delta.new_file().is_binary() || delta.old_file().is_binary() {
+        return Ok(FileDiff {
+            path: file_path,
+            status,
+            old_content: None,
+            new_content: None,
+            diff_lines: Vec::new(),
+            is_binary: true,
+        });
+    }
+    
+    let mut patch_lines = Vec::new();
+    
+    let patch = git2::Patch::from_diff(diff, delta_idx).map_err(|e| format!("Failed to create patch: {}", e))?;
+    
+    if let Some(patch) = patch {
+        // libgit2 only detects binary content while building the patch
+        if patch.delta().flags().is_binary() {
+            return Ok(FileDiff {
+                path: file_path,
+                status,
+                old_content: None,
+                new_content: None,
+                diff_lines: Vec::new(),
+                is_binary: true,
+            });
+        }
+        
+        for hunk_idx in 0..patch.num_hunks() {
+            let (_hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| format!("Failed to get hunk: {}", e))?;
+            
+            for line_idx in 0..hunk_lines {
+                let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| format!("Failed to get line: {}", e))?;
+                
+                let line_content = String::from_utf8_lossy(line.content()).trim_end_matches('\n').to_string();
+                let line_type = match line.origin() {
+                    '+' => "addition",
+                    '-' => "deletion",
+                    ' ' => "context",
+                    _ => "context",
+                };
+                
+                patch_lines.push(DiffLine {
+                    line_type: line_type.to_string(),
+                    content: line_content,
+                    old_line_number: line.old_lineno(),
+                    new_line_number: line.new_lineno(),
+                    hunk_index: hunk_idx as u32,
+                });
+            }
+        }
+    }
+    
+    Ok(FileDiff {
+        path: file_path,
+        status,
+        old_content: None,
+        new_content: None,
+        diff_lines: patch_lines,
+        is_binary: false,
+    })
+}
+
+#[tauri::command]
+fn stage_file(sessions: State<'_, RepoSessions>, path: String, file_path: String) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let workdir = repo.workdir().ok_or("Cannot stage files in a bare repository")?;
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    index.read(false).map_err(|e| format!("Failed to read index: {}", e))?;
+    
+    // A file renamed in the working tree also stages the removal of its old path
+    if let Some(old_path) = working_tree_renames(&repo)?.remove(&file_path) {
+        index.remove_path(Path::new(&old_path)).map_err(|e| format!("Failed to stage '{}': {}", old_path, e))?;
+    }
+    
+    // A file missing from the working tree stages its deletion
+    if std::fs::symlink_metadata(workdir.join(&file_path)).is_ok() {
+        index.add_path(Path::new(&file_path)).map_err(|e| format!("Failed to stage '{}': {}", file_path, e))?;
+    } else {
+        index.remove_path(Path::new(&file_path)).map_err(|e| format!("Failed to stage '{}': {}", file_path, e))?;
+    }
+    
+    index.write().map_err(|e| format!("Failed to write index: {}", e))
+}
+
+#[tauri::command]
+fn unstage_file(sessions: State<'_, RepoSessions>, path: String, file_path: String) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let head_commit = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit().map_err(|e| e.to_string())?),
+        Err(_) => None, // Repository has no commits yet
+    };
+    
+    if let Some(head_commit) = head_commit {
+        repo.reset_default(Some(head_commit.as_object()), [file_path.as_str()])
+            .map_err(|e| format!("Failed to unstage '{}': {}", file_path, e))?;
+    } else {
+        // Nothing committed yet, so unstaging removes the file from the index
+        let mut index = repo.index().map_err(|e| e.to_string())?;
+        index.read(false).map_err(|e| format!("Failed to read index: {}", e))?;
+        index.remove_path(Path::new(&file_path)).map_err(|e| format!("Failed to unstage '{}': {}", file_path, e))?;
+        index.write().map_err(|e| format!("Failed to write index: {}", e))?;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn stage_hunk(sessions: State<'_, RepoSessions>, path: String, file_path: String, hunk_index: u32) -> Result<(), String> {
+    stage_selected_lines(&sessions, &path, &file_path, &|hunk_idx, _line| hunk_idx == hunk_index as usize)
+}
+
+#[tauri::command]
+fn unstage_hunk(sessions: State<'_, RepoSessions>, path: String, file_path: String, hunk_index: u32) -> Result<(), String> {
+    unstage_selected_lines(&sessions, &path, &file_path, &|hunk_idx, _line| hunk_idx == hunk_index as usize)
+}
+
+#[tauri::command]
+fn stage_lines(sessions: State<'_, RepoSessions>, path: String, file_path: String, lines: Vec<LineSelection>) -> Result<(), String> {
+    stage_selected_lines(&sessions, &path, &file_path, &|_hunk_idx, line| is_line_selected(&lines, line))
+}
+
+#[tauri::command]
+fn unstage_lines(sessions: State<'_, RepoSessions>, path: String, file_path: String, lines: Vec<LineSelection>) -> Result<(), String> {
+    unstage_selected_lines(&sessions, &path, &file_path, &|_hunk_idx, line| is_line_selected(&lines, line))
+}
+
+/// First step of discarding working tree changes: lists what would be lost
+/// and returns a token describing the current state of the files. The
+/// frontend shows the list for confirmation and passes the token back to
+/// `discard_changes`, which refuses to run if the files changed in between.
+#[tauri::command]
+fn prepare_discard(sessions: State<'_, RepoSessions>, path: String, file_paths: Vec<String>) -> Result<DiscardPlan, String> {
+    let changes = get_working_tree_changes(sessions.clone(), path.clone())?;
+    
+    let mut files = Vec::new();
+    for file_path in &file_paths {
+        let change = changes.iter()
+            .find(|change| &change.path == file_path)
+            .ok_or_else(|| format!("'{}' has no working tree changes to discard", file_path))?;
+        if change.status == "conflicted" {
+            return Err(unmerged_error(file_path));
+        }
+        files.push(WorkingTreeChange {
+            path: change.path.clone(),
+            status: change.status.clone(),
+            old_path: change.old_path.clone(),
+            additions: change.additions,
+            deletions: change.deletions,
+        });
+    }
+    
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    let token = discard_token(&repo, &with_rename_sources(&repo, file_paths)?)?;
+    
+    Ok(DiscardPlan {
+        files,
+        token,
+    })
+}
+
+/// Restores tracked files to their staged version and deletes untracked ones.
+/// Conflicted files are refused, they have no staged version to restore.
+#[tauri::command]
+fn discard_changes(sessions: State<'_, RepoSessions>, path: String, file_paths: Vec<String>, token: String) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    // Discarding a rename restores the old path and deletes the new one
+    let file_paths = with_rename_sources(&repo, file_paths)?;
+    if discard_token(&repo, &file_paths)? != token {
+        return Err("Files changed since the discard was confirmed, please review them again".to_string());
+    }
+    
+    let workdir = repo.workdir().ok_or("Cannot discard changes in a bare repository")?.to_path_buf();
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    index.read(false).map_err(|e| format!("Failed to read index: {}", e))?;
+    
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force();
+    checkout.disable_pathspec_match(true);
+    let mut has_tracked = false;
+    
+    // A conflicted path has no stage 0 entry but is not untracked either,
+    // refuse it like `git checkout -- <path>` does before touching anything
+    if let Some(file_path) = file_paths.iter().find(|file_path| {
+        (1..=3).any(|stage| index.get_path(Path::new(file_path.as_str()), stage).is_some())
+    }) {
+        return Err(unmerged_error(file_path));
+    }
+    
+    for file_path in &file_paths {
+        if index.get_path(Path::new(file_path), 0).is_some() {
+            checkout.path(file_path);
+            has_tracked = true;
+        } else {
+            std::fs::remove_file(workdir.join(file_path))
+                .map_err(|e| format!("Failed to delete '{}': {}", file_path, e))?;
+        }
+    }
+    
+    if has_tracked {
+        repo.checkout_index(Some(&mut index), Some(&mut checkout))
+            .map_err(|e| format!("Failed to discard changes: {}", e))?;
+    }
+    
+    Ok(())
+}
+
+/// `file_paths` plus the old path of each one that was renamed in the working tree.
+fn with_rename_sources(repo: &git2::Repository, mut file_paths: Vec<String>) -> Result<Vec<String>, String> {
+    let mut renames = working_tree_renames(repo)?;
+    let old_paths: Vec<String> = file_paths.iter().filter_map(|file_path| renames.remove(file_path)).collect();
+    
+    for old_path in old_paths {
+        if !file_paths.contains(&old_path) {
+            file_paths.push(old_path);
+        }
+    }
+    Ok(file_paths)
+}
+
+fn unmerged_error(file_path: &str) -> String {
+    format!("Cannot discard '{}': path is unmerged, resolve the conflict first", file_path)
+}
+
+/// Hash of the working tree and index versions of `file_paths`.
+fn discard_token(repo: &git2::Repository, file_paths: &[String]) -> Result<String, String> {
+    let workdir = repo.workdir().ok_or("Cannot discard changes in a bare repository")?;
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    index.read(false).map_err(|e| format!("Failed to read index: {}", e))?;
+    
+    let mut sorted_paths: Vec<&String> = file_paths.iter().collect();
+    sorted_paths.sort();
+    
+    let mut state = String::new();
+    for file_path in sorted_paths {
+        let workdir_id = git2::Oid::hash_file(git2::ObjectType::Blob, workdir.join(file_path))
+            .map(|oid| oid.to_string())
+            .unwrap_or_else(|_| "missing".to_string());
+        let index_id = index.get_path(Path::new(file_path), 0)
+            .map(|entry| entry.id.to_string())
+            .unwrap_or_else(|| "untracked".to_string());
+        state.push_str(&format!("{} {} {}\n", file_path, workdir_id, index_id));
+    }
+    
+    git2::Oid::hash_object(git2::ObjectType::Blob, state.as_bytes())
+        .map(|oid| oid.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn is_line_selected(lines: &[LineSelection], line: &git2::DiffLine) -> bool {
+    lines.iter().any(|selection| match line.origin() {
+        '+' => selection.old_line_number.is_none() && selection.new_line_number == line.new_lineno(),
+        '-' => selection.new_line_number.is_none() && selection.old_line_number == line.old_lineno(),
+        _ => false,
+    })
+}
+
+/// Stages the unstaged changes of `file_path` picked by `select`, leaving the
+/// rest in the working tree.
+fn stage_selected_lines(sessions: &RepoSessions, path: &str, file_path: &str, select: &dyn Fn(usize, &git2::DiffLine) -> bool) -> Result<(), String> {
+    let handle = sessions.open(path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    index.read(false).map_err(|e| format!("Failed to read index: {}", e))?;
+    
+    let mut diff_opts = working_tree_diff_options();
+    diff_opts.pathspec(file_path);
+    diff_opts.disable_pathspec_match(true);
+    let diff = repo.diff_index_to_workdir(Some(&index), Some(&mut diff_opts)).map_err(|e| format!("Failed to create diff: {}", e))?;
+    let patch = file_patch(&diff, file_path)?.ok_or_else(|| format!("'{}' has no unstaged changes", file_path))?;
+    
+    // The index version is the old side of the index-vs-workdir diff
+    let existing_entry = index.get_path(Path::new(file_path), 0);
+    let old_content = match &existing_entry {
+        Some(entry) => repo.find_blob(entry.id).map_err(|e| e.to_string())?.content().to_vec(),
+        None => Vec::new(),
+    };
+    let new_content = apply_selected_lines(&old_content, &patch, select)?;
+    
+    // Staging every line of a deleted file stages the deletion itself
+    if patch.delta().status() == git2::Delta::Deleted && new_content.is_empty() {
+        index.remove_path(Path::new(file_path)).map_err(|e| format!("Failed to stage '{}': {}", file_path, e))?;
+        return index.write().map_err(|e| format!("Failed to write index: {}", e));
+    }
+    
+    let entry = existing_entry.unwrap_or_else(|| new_index_entry(file_path, patch.delta().new_file().mode()));
+    index.add_frombuffer(&entry, &new_content).map_err(|e| format!("Failed to stage '{}': {}", file_path, e))?;
+    index.write().map_err(|e| format!("Failed to write index: {}", e))
+}
+
+/// Unstages the staged changes of `file_path` picked by `select`, keeping the
+/// rest staged. The working tree is left untouched.
+fn unstage_selected_lines(sessions: &RepoSessions, path: &str, file_path: &str, select: &dyn Fn(usize, &git2::DiffLine) -> bool) -> Result<(), String> {
+    let handle = sessions.open(path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    index.read(false).map_err(|e| format!("Failed to read index: {}", e))?;
+    
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree().map_err(|e| e.to_string())?),
+        Err(_) => None, // Repository has no commits yet
+    };
+    
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.pathspec(file_path);
+    diff_opts.disable_pathspec_match(true);
+    let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), Some(&mut diff_opts)).map_err(|e| format!("Failed to create diff: {}", e))?;
+    let patch = file_patch(&diff, file_path)?.ok_or_else(|| format!("'{}' has no staged changes", file_path))?;
+    
+    // Rebuild the index version from HEAD with every staged change except
+    // the selected ones
+    let old_content = match head_tree.as_ref().and_then(|tree| tree.get_path(Path::new(file_path)).ok()) {
+        Some(tree_entry) => repo.find_blob(tree_entry.id()).map_err(|e| e.to_string())?.content().to_vec(),
+        None => Vec::new(),
+    };
+    let new_content = apply_selected_lines(&old_content, &patch, &|hunk_idx, line| !select(hunk_idx, line))?;
+    
+    // Unstaging every line of an added file takes it out of the index again
+    if patch.delta().status() == git2::Delta::Added && new_content.is_empty() {
+        index.remove_path(Path::new(file_path)).map_err(|e| format!("Failed to unstage '{}': {}", file_path, e))?;
+        return index.write().map_err(|e| format!("Failed to write index: {}", e));
+    }
+    
+    let entry = index.get_path(Path::new(file_path), 0)
+        .unwrap_or_else(|| new_index_entry(file_path, patch.delta().old_file().mode()));
+    index.add_frombuffer(&entry, &new_content).map_err(|e| format!("Failed to unstage '{}': {}", file_path, e))?;
+    index.write().map_err(|e| format!("Failed to write index: {}", e))
+}
+
+/// The text patch of `file_path` in `diff`, if the file changed.
+fn file_patch<'a>(diff: &'a git2::Diff, file_path: &str) -> Result<Option<git2::Patch<'a>>, String> {
+    let delta_idx = diff.deltas().position(|delta| {
+        delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str()) == Some(file_path)
+    });
+    
+    let Some(delta_idx) = delta_idx else {
+        return Ok(None);
+    };
+    
+    let patch = git2::Patch::from_diff(diff, delta_idx).map_err(|e| format!("Failed to create patch: {}", e))?;
+    match patch {
+        Some(patch) if !patch.delta().flags().is_binary() => Ok(Some(patch)),
+        _ => Err(format!("Cannot select lines of binary file '{}'", file_path)),
+    }
+}
+
+fn new_index_entry(file_path: &str, mode: git2::FileMode) -> git2::IndexEntry {
+    let mode = match mode {
+        git2::FileMode::Unreadable => git2::FileMode::Blob,
+        mode => mode,
+    };
+    
+    git2::IndexEntry {
+        ctime: git2::IndexTime::new(0, 0),
+        mtime: git2::IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: u32::from(mode),
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id: git2::Oid::zero(),
+        flags: 0,
+        flags_extended: 0,
+        path: file_path.as_bytes().to_vec(),
+    }
+}
+
+/// Rebuilds a file from `old_content`, the old side of `patch`, applying only
+/// the added and deleted lines picked by `select`. Unpicked deletions stay in
+/// the file and unpicked additions are left out.
+fn apply_selected_lines(old_content: &[u8], patch: &git2::Patch, select: &dyn Fn(usize, &git2::DiffLine) -> bool) -> Result<Vec<u8>, String> {
+    let old_lines: Vec<&[u8]> = old_content.split_inclusive(|&b| b == b'\n').collect();
+    let mut result = Vec::with_capacity(old_content.len());
+    let mut next_old = 0; // Index of the next old line not yet copied
+    
+    for hunk_idx in 0..patch.num_hunks() {
+        let (_hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| format!("Failed to get hunk: {}", e))?;
+        
+        for line_idx in 0..hunk_lines {
+            let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| format!("Failed to get line: {}", e))?;
+            
+            match line.origin() {
+                ' ' | '-' => {
+                    let old = line.old_lineno().ok_or("Diff line without an old line number")? as usize - 1;
+                    if old >= old_lines.len() || old < next_old {
+                        return Err("Diff does not match the file content".to_string());
+                    }
+                    
+                    // Copy the unchanged lines up to this one
+                    for unchanged in &old_lines[next_old..old] {
+                        result.extend_from_slice(unchanged);
+                    }
+                    if line.origin() == ' ' || !select(hunk_idx, &line) {
+                        result.extend_from_slice(old_lines[old]);
+                    }
+                    next_old = old + 1;
+                }
+                '+' if select(hunk_idx, &line) => {
+                    // The kept line may have been the last one, without a newline
+                    if !result.is_empty() && !result.ends_with(b"\n") {
+                        result.push(b'\n');
+                    }
+                    result.extend_from_slice(line.content());
+                }
+                _ => {} // Unselected additions and "No newline at end of file" markers
+            }
+        }
+    }
+    
+    for unchanged in &old_lines[next_old..] {
+        result.extend_from_slice(unchanged);
+    }
+    
+    Ok(result)
+}
+
+/// Commits the staged index on top of HEAD (or replaces HEAD when amending),
+/// using the identity from git config. Runs the pre-commit and commit-msg
+/// hooks unless `no_verify` is set.
+#[tauri::command]
+fn create_commit(sessions: State<'_, RepoSessions>, path: String, message: String, amend: Option<bool>, no_verify: Option<bool>) -> Result<GitCommit, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    let amend = amend.unwrap_or(false);
+    let run_hooks = !no_verify.unwrap_or(false);
+    
+    let signature = repo.signature().map_err(|e| {
+        format!("Cannot commit without an identity, set user.name and user.email in git config: {}", e)
+    })?;
+    
+    // A merge is concluded by the commit. The state of any other operation
+    // (rebase, cherry-pick, ...) would be lost, so leave those to git.
+    let concludes_merge = match repo.state() {
+        git2::RepositoryState::Clean => false,
+        git2::RepositoryState::Merge if !amend => true,
+        git2::RepositoryState::Merge => return Err("Cannot amend in the middle of a merge".to_string()),
+        state => return Err(format!("Cannot commit while {} is in progress, finish or abort it first", operation_name(state))),
+    };
+    
+    // Concluding a merge commits all merge heads as extra parents
+    let mut merge_heads = Vec::new();
+    if concludes_merge {
+        repo.mergehead_foreach(|oid| {
+            merge_heads.push(*oid);
+            true
+        }).map_err(|e| e.to_string())?;
+    }
+    
+    if run_hooks {
+        run_hook(&repo, "pre-commit", &[])?;
+    }
+    
+    // The commit-msg hook may rewrite the message file, like git commit does
+    let message = git2::message_prettify(message, Some(b'#')).map_err(|e| e.to_string())?;
+    let message_path = repo.path().join("COMMIT_EDITMSG");
+    std::fs::write(&message_path, &message).map_err(|e| format!("Failed to write commit message: {}", e))?;
+    if run_hooks {
+        run_hook(&repo, "commit-msg", &[message_path.to_string_lossy().as_ref()])?;
+    }
+    let message = std::fs::read_to_string(&message_path).map_err(|e| format!("Failed to read commit message: {}", e))?;
+    let message = git2::message_prettify(message, Some(b'#')).map_err(|e| e.to_string())?;
+    if message.trim().is_empty() {
+        return Err("Aborting commit due to empty commit message".to_string());
+    }
+    
+    // Hooks may have staged more changes, so reload the index before writing it
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    index.read(true).map_err(|e| format!("Failed to read index: {}", e))?;
+    if index.has_conflicts() {
+        return Err("Cannot commit with unresolved conflicts".to_string());
+    }
+    let tree_id = index.write_tree().map_err(|e| format!("Failed to write tree: {}", e))?;
+    let tree = repo.find_tree(tree_id).map_err(|e| e.to_string())?;
+    
+    let head_commit = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit().map_err(|e| e.to_string())?),
+        Err(_) => None, // First commit
+    };
+    
+    let commit_id = if amend {
+        let head_commit = head_commit.ok_or("There is no commit to amend")?;
+        // Keep the original author, like git commit --amend
+        head_commit.amend(Some("HEAD"), None, Some(&signature), None, Some(&message), Some(&tree))
+            .map_err(|e| format!("Failed to amend commit: {}", e))?
+    } else {
+        if merge_heads.is_empty() {
+            let unchanged = match &head_commit {
+                Some(commit) => commit.tree_id() == tree_id,
+                None => tree.is_empty(),
+            };
+            if unchanged {
+                return Err("Nothing to commit, stage some changes first".to_string());
+            }
+        }
+        
+        let mut parents = Vec::new();
+        parents.extend(head_commit);
+        for oid in merge_heads {
+            parents.push(repo.find_commit(oid).map_err(|e| e.to_string())?);
+        }
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        
+        repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parent_refs)
+            .map_err(|e| format!("Failed to create commit: {}", e))?
+    };
+    
+    // A finished merge leaves MERGE_HEAD and friends behind
+    if concludes_merge {
+        repo.cleanup_state().map_err(|e| e.to_string())?;
+    }
+    
+    if run_hooks {
+        // Like git, a failing post-commit hook doesn't undo the commit
+        let _ = run_hook(&repo, "post-commit", &[]);
+    }
+    
+    let commit = repo.find_commit(commit_id).map_err(|e| e.to_string())?;
+    Ok(to_git_commit(&commit))
+}
+
+fn operation_name(state: git2::RepositoryState) -> &'static str {
+    use git2::RepositoryState::*;
+    match state {
+        Clean => "nothing",
+        Merge => "a merge",
+        Revert | RevertSequence => "a revert",
+        CherryPick | CherryPickSequence => "a cherry-pick",
+        Bisect => "a bisect",
+        Rebase | RebaseInteractive | RebaseMerge | ApplyMailboxOrRebase => "a rebase",
+        ApplyMailbox => "git am",
+    }
+}
+
+/// Runs the hook `name` from the repository's hooks directory, if it exists
+/// and is executable. A non-zero exit becomes an error carrying the hook output.
+fn run_hook(repo: &git2::Repository, name: &str, args: &[&str]) -> Result<(), String> {
+    let work_dir = repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf();
+    
+    let hooks_dir = match repo.config().ok().and_then(|config| config.get_path("core.hooksPath").ok()) {
+        Some(dir) if dir.is_absolute() => dir,
+        Some(dir) => work_dir.join(dir),
+        None => repo.commondir().join("hooks"),
+    };
+    let hook = hooks_dir.join(name);
+    
+    let is_executable = match std::fs::metadata(&hook) {
+        #[cfg(unix)]
+        Ok(metadata) => {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+        }
+        #[cfg(not(unix))]
+        Ok(metadata) => metadata.is_file(),
+        Err(_) => false,
+    };
+    if !is_executable {
+        return Ok(());
+    }
+    
+    // Hooks are usually shell scripts, which Windows can't execute directly
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = std::process::Command::new("sh");
+        command.arg(&hook);
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = std::process::Command::new(&hook);
+    
+    let output = command
+        .args(args)
+        .current_dir(&work_dir)
+        .env("GIT_DIR", repo.path())
+        .env("GIT_INDEX_FILE", repo.path().join("index"))
+        .output()
+        .map_err(|e| format!("Failed to run {} hook: {}", name, e))?;
+    
+    if output.status.success() {
+        Ok(())
+    } else {
+        let hook_output = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+        Err(format!("{} hook failed: {}", name, hook_output.trim_end()))
+    }
+}
+
+#[tauri::command]
+fn get_stashes(sessions: State<'_, RepoSessions>, path: String) -> Result<Vec<GitStash>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    let mut stashes = Vec::new();
+    
+    // stash_foreach borrows the repository mutably, so collect the entries
+    // first and look up their commits afterwards
+    let mut entries = Vec::new();
+    repo.stash_foreach(|index, message, stash_id| {
+        entries.push((index, message.to_string(), *stash_id));
+        true // Continue iteration
+    }).map_err(|e| e.to_string())?;
+    
+    for (index, message, stash_id) in entries {
+        // Get the stash commit
+        if let Ok(stash_commit) = repo.find_commit(stash_id) {
+            let author = stash_commit.author();
+            let timestamp = stash_commit.time();
+            
+            // Format the date
+            let datetime = chrono::DateTime::from_timestamp(timestamp.seconds(), 0)
+                .unwrap_or_else(|| chrono::DateTime::from_timestamp(0, 0).unwrap());
+            let formatted_date = datetime.format("%Y-%m-%d %H:%M:%S").to_string();
+            
+            stashes.push(GitStash {
+                index: index as u32,
+                message,
+                commit_id: stash_id.to_string(),
+                author: format!("{} <{}>", 
+                    author.name().unwrap_or("Unknown"), 
+                    author.email().unwrap_or("unknown@email.com")),
+                date: formatted_date,
+            });
+        }
+    }
+    
+    Ok(stashes)
+}
+
+#[tauri::command]
+fn get_stash_diff(sessions: State<'_, RepoSessions>, path: String, stash_index: u32) -> Result<Vec<FileChange>, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    // Get the stash commit by index
+    let mut stash_commit_id = None;
+    let mut current_index = 0;
+    
+    repo.stash_foreach(|index, _message, stash_id| {
+        if index as u32 == stash_index {
+            stash_commit_id = Some(*stash_id);
+            false // Stop iteration
+        } else {
+            current_index = index;
+            true // Continue iteration
+        }
+    }).map_err(|e| e.to_string())?;
+    
+    let stash_oid = stash_commit_id.ok_or("Stash not found")?;
+    let stash_commit = repo.find_commit(stash_oid).map_err(|e| e.to_string())?;
+    
+    // Get the parent commit (the commit the stash was based on)
+    let parent_commit = stash_commit.parent(0).map_err(|e| e.to_string())?;
+    
+    // Create diff between parent and stash
+    let parent_tree = parent_commit.tree().map_err(|e| e.to_string())?;
+    let stash_tree = stash_commit.tree().map_err(|e| e.to_string())?;
+    
+    let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&stash_tree), None)
+        .map_err(|e| e.to_string())?;
+    
+    collect_file_changes(&diff)
+}
+
+#[tauri::command]
+fn get_stash_file_diff(sessions: State<'_, RepoSessions>, path: String, stash_index: u32, file_path: String) -> Result<FileDiff, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    // Get the stash commit by index
+    let mut stash_commit_id = None;
+    
+    repo.stash_foreach(|index, _message, stash_id| {
+        if index as u32 == stash_index {
+            stash_commit_id = Some(*stash_id);
+            false // Stop iteration
+        } else {
+            true // Continue iteration
+        }
+    }).map_err(|e| e.to_string())?;
+    
+    let stash_oid = stash_commit_id.ok_or("Stash not found")?;
+    let stash_commit = repo.find_commit(stash_oid).map_err(|e| e.to_string())?;
+    
+    // Get the parent commit
+    let parent_commit = stash_commit.parent(0).map_err(|e| e.to_string())?;
+    
+    // Create diff between parent and stash for specific file
+    let parent_tree = parent_commit.tree().map_err(|e| e.to_string())?;
+    let stash_tree = stash_commit.tree().map_err(|e| e.to_string())?;
+    
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.context_lines(3);
+    diff_opts.pathspec(file_path.clone());
+    
+    let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&stash_tree), Some(&mut diff_opts))
+        .map_err(|e| e.to_string())?;
+    
+    // Find the specific file in the diff
+    let mut file_found = false;
+    let mut file_status = "unknown";
+    let mut is_binary = false;
+    
+    for (_delta_idx, delta) in diff.deltas().enumerate() {
+        let delta_path = delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str())
+            .unwrap_or("unknown");
+        
+        if delta_path == file_path {
+            file_found = true;
+            file_status = match delta.status() {
+                git2::Delta::Added => "added",
+                git2::Delta::Deleted => "deleted", 
+                git2::Delta::Modified => "modified",
+                git2::Delta::Renamed => "renamed",
+                git2::Delta::Copied => "copied",
+                _ => "unknown",
+            };
+            is_binary = delta.new_file().is_binary() || delta.old_file().is_binary();
+            break;
+        }
+    }
+    
+    if !file_found {
+        return Err(format!("File '{}' not found in stash changes", file_path));
+    }
+    
+    if is_binary {
+        return Ok(FileDiff {
+            path: file_path,
+            status: file_status.to_string(),
+            old_content: None,
+            new_content: None,
+            diff_lines: Vec::new(),
+            is_binary: true,
+        });
+    }
+    
+    // Generate patch for text files
+    let mut patch_lines = Vec::new();
+    
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        let delta_path = delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str())
+            .unwrap_or("unknown");
+        
+        if delta_path == file_path {
+            let patch = git2::Patch::from_diff(&diff, delta_idx).map_err(|e| format!("Failed to create patch: {}", e))?;
+            
+            if let Some(patch) = patch {
+                for hunk_idx in 0..patch.num_hunks() {
+                    let (_hunk, hunk_lines) = patch.hunk(hunk_idx).map_err(|e| format!("Failed to get hunk: {}", e))?;
+                    
+                    for line_idx in 0..hunk_lines {
+                        let line = patch.line_in_hunk(hunk_idx, line_idx).map_err(|e| format!("Failed to get line: {}", e))?;
+                        
+                        let line_content = String::from_utf8_lossy(line.content()).trim_end_matches('\n').to_string();
+                        let line_type = match line.origin() {
+                            '+' => "addition",
+                            '-' => "deletion", 
+                            ' ' => "context",
+                            _ => "context",
+                        };
+                        
+                        patch_lines.push(DiffLine {
+                            line_type: line_type.to_string(),
+                            content: line_content,
+                            old_line_number: line.old_lineno(),
+                            new_line_number: line.new_lineno(),
+                            hunk_index: hunk_idx as u32,
+                        });
+                    }
+                }
+            }
+            break;
+        }
+    }
+    
+    Ok(FileDiff {
+        path: file_path,
+        status: file_status.to_string(),
+        old_content: None,
+        new_content: None,
+        diff_lines: patch_lines,
+        is_binary: false,
+    })
+}
+
+#[tauri::command]
+fn get_stash_changes(sessions: State<'_, RepoSessions>, path: String, stash_index: u32) -> Result<StashChanges, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    let stash_oid = find_stash(&mut repo, stash_index)?;
+    let stash_commit = repo.find_commit(stash_oid).map_err(|e| e.to_string())?;
+    
+    let staged = stash_part_diff(&repo, &stash_commit, "staged")?;
+    let unstaged = stash_part_diff(&repo, &stash_commit, "unstaged")?;
+    let untracked = stash_part_diff(&repo, &stash_commit, "untracked")?;
+    
+    Ok(StashChanges {
+        staged: collect_file_changes(&staged)?,
+        unstaged: collect_file_changes(&unstaged)?,
+        untracked: collect_file_changes(&untracked)?,
+    })
+}
+
+#[tauri::command]
+fn get_stash_part_file_diff(sessions: State<'_, RepoSessions>, path: String, stash_index: u32, part: String, file_path: String) -> Result<FileDiff, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    let stash_oid = find_stash(&mut repo, stash_index)?;
+    let stash_commit = repo.find_commit(stash_oid).map_err(|e| e.to_string())?;
+    
+    let diff = stash_part_diff(&repo, &stash_commit, &part)?;
+    build_file_diff(&diff, file_path, &format!("{} stash changes", part))
+}
+
+// A stash commit has the base commit as parent 1, the stashed index as
+// parent 2 and, when untracked files were included, a root commit holding
+// just those files as parent 3
+fn stash_part_diff<'r>(repo: &'r git2::Repository, stash_commit: &git2::Commit, part: &str) -> Result<git2::Diff<'r>, String> {
+    let parent_tree = |n: usize| stash_commit.parent(n).and_then(|parent| parent.tree());
+    
+    let (old_tree, new_tree) = match part {
+        "staged" => (Some(parent_tree(0).map_err(|e| e.to_string())?), Some(parent_tree(1).map_err(|e| e.to_string())?)),
+        "unstaged" => (Some(parent_tree(1).map_err(|e| e.to_string())?), Some(stash_commit.tree().map_err(|e| e.to_string())?)),
+        "untracked" if stash_commit.parent_count() > 2 => (None, Some(parent_tree(2).map_err(|e| e.to_string())?)),
+        "untracked" => (None, None),
+        _ => return Err(format!("Unknown stash part '{}', expected staged, unstaged or untracked", part)),
+    };
+    
+    repo.diff_tree_to_tree(old_tree.as_ref(), new_tree.as_ref(), None).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn stash_push(sessions: State<'_, RepoSessions>, path: String, message: Option<String>, include_untracked: Option<bool>, keep_index: Option<bool>) -> Result<GitStash, String> {
+    {
+        let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+        let mut repo = handle.lock();
+        
+        let signature = repo.signature().map_err(|e| {
+            format!("Cannot stash without an identity, set user.name and user.email in git config: {}", e)
+        })?;
+        
+        let mut flags = git2::StashFlags::DEFAULT;
+        if include_untracked.unwrap_or(false) {
+            flags |= git2::StashFlags::INCLUDE_UNTRACKED;
+        }
+        if keep_index.unwrap_or(false) {
+            flags |= git2::StashFlags::KEEP_INDEX;
+        }
+        
+        let message = message.filter(|m| !m.trim().is_empty());
+        repo.stash_save2(&signature, message.as_deref(), Some(flags)).map_err(|e| match e.code() {
+            git2::ErrorCode::NotFound => "No local changes to save".to_string(),
+            _ => format!("Failed to stash changes: {}", e),
+        })?;
+    }
+    
+    // The new stash is always stash@{0}
+    get_stashes(sessions, path)?.into_iter().next().ok_or_else(|| "Stash not found".to_string())
+}
+
+#[tauri::command]
+fn stash_apply(sessions: State<'_, RepoSessions>, path: String, stash_index: u32, reinstate_index: Option<bool>) -> Result<StashApplyResult, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    let conflicts = apply_stash(&mut repo, stash_index, reinstate_index.unwrap_or(false))?;
+    
+    Ok(StashApplyResult { conflicts, dropped: false })
+}
+
+#[tauri::command]
+fn stash_pop(sessions: State<'_, RepoSessions>, path: String, stash_index: u32, reinstate_index: Option<bool>) -> Result<StashApplyResult, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    let conflicts = apply_stash(&mut repo, stash_index, reinstate_index.unwrap_or(false))?;
+    
+    // Like git stash pop, keep the stash around when applying it conflicted
+    let dropped = conflicts.is_empty();
+    if dropped {
+        repo.stash_drop(stash_index as usize).map_err(|e| format!("Failed to drop stash: {}", e))?;
+    }
+    
+    Ok(StashApplyResult { conflicts, dropped })
+}
+
+#[tauri::command]
+fn stash_drop(sessions: State<'_, RepoSessions>, path: String, stash_index: u32) -> Result<(), String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    find_stash(&mut repo, stash_index)?;
+    repo.stash_drop(stash_index as usize).map_err(|e| format!("Failed to drop stash: {}", e))
+}
+
+#[tauri::command]
+fn stash_branch(sessions: State<'_, RepoSessions>, path: String, stash_index: u32, branch_name: String) -> Result<StashApplyResult, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let mut repo = handle.lock();
+    
+    if !git2::Branch::name_is_valid(&branch_name).map_err(|e| e.to_string())? {
+        return Err(format!("'{}' is not a valid branch name", branch_name));
+    }
+    
+    // The branch starts at the commit the stash was created on
+    let stash_oid = find_stash(&mut repo, stash_index)?;
+    let base_oid = repo.find_commit(stash_oid)
+        .and_then(|stash_commit| stash_commit.parent_id(0))
+        .map_err(|e| e.to_string())?;
+    let base_commit = repo.find_commit(base_oid).map_err(|e| e.to_string())?;
+    
+    // Where to return to if the stash can't be applied on the new branch
+    let previous_head = repo.head().ok().and_then(|head| {
+        let reference_name = if head.is_branch() { head.name().map(|name| name.to_string()) } else { None };
+        Some((head.target()?, reference_name))
+    });
+    
+    repo.branch(&branch_name, &base_commit, false).map_err(|e| format!("Failed to create branch: {}", e))?;
+    checkout_commit_tree(&repo, &base_commit, Some(&format!("refs/heads/{}", branch_name)))
+        .inspect_err(|_| remove_new_branch(&repo, &branch_name))?;
+    drop(base_commit);
+    
+    let conflicts = match apply_stash(&mut repo, stash_index, true) {
+        Ok(conflicts) => conflicts,
+        Err(e) => {
+            // The base commit's tree is still checked out untouched, so going
+            // back is a plain checkout
+            if let Some((previous_oid, reference_name)) = previous_head {
+                if let Ok(previous_commit) = repo.find_commit(previous_oid) {
+                    let _ = checkout_commit_tree(&repo, &previous_commit, reference_name.as_deref());
+                }
+            }
+            remove_new_branch(&repo, &branch_name);
+            return Err(e);
+        }
+    };
+    let dropped = conflicts.is_empty();
+    if dropped {
+        repo.stash_drop(stash_index as usize).map_err(|e| format!("Failed to drop stash: {}", e))?;
+    }
+    
+    Ok(StashApplyResult { conflicts, dropped })
+}
+
+fn find_stash(repo: &mut git2::Repository, stash_index: u32) -> Result<git2::Oid, String> {
+    let mut stash_commit_id = None;
+    
+    repo.stash_foreach(|index, _message, stash_id| {
+        if index as u32 == stash_index {
+            stash_commit_id = Some(*stash_id);
+            false // Stop iteration
+        } else {
+            true // Continue iteration
+        }
+    }).map_err(|e| e.to_string())?;
+    
+    stash_commit_id.ok_or_else(|| format!("Stash@{{{}}} not found", stash_index))
+}
+
+// Applies a stash and returns the paths it left conflicted
+fn apply_stash(repo: &mut git2::Repository, stash_index: u32, reinstate_index: bool) -> Result<Vec<String>, String> {
+    find_stash(repo, stash_index)?;
+    
+    let mut options = git2::StashApplyOptions::new();
+    if reinstate_index {
+        options.reinstantiate_index();
+    }
+    
+    repo.stash_apply(stash_index as usize, Some(&mut options)).map_err(|e| match e.code() {
+        git2::ErrorCode::Conflict | git2::ErrorCode::MergeConflict => {
+            format!("Your local changes would be overwritten by applying the stash, commit or stash them first: {}", e.message())
+        }
+        _ => format!("Failed to apply stash: {}", e),
+    })?;
+    
+    let index = repo.index().map_err(|e| e.to_string())?;
+    let mut conflicts = Vec::new();
+    for conflict in index.conflicts().map_err(|e| e.to_string())? {
+        let conflict = conflict.map_err(|e| e.to_string())?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            conflicts.push(String::from_utf8_lossy(&entry.path).to_string());
+        }
+    }
+    
+    Ok(conflicts)
+}
+
+#[tauri::command]
+fn get_file_content(sessions: State<'_, RepoSessions>, path: String, commit_id: String, file_path: String) -> Result<String, String> {
+    let handle = sessions.open(&path).map_err(|e| e.to_string())?;
+    let repo = handle.lock();
+    
+    let oid = git2::Oid::from_str(&commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+    
+    // Find the file in the tree
+    let tree_entry = tree.get_path(Path::new(&file_path)).map_err(|e| {
+        format!("File '{}' not found in commit '{}': {}", file_path, commit_id, e)
+    })?;
+    
+    // Get the blob
+    let blob = repo.find_blob(tree_entry.id()).map_err(|e| e.to_string())?;
+    
+    // Check if file is binary
+    if blob.is_binary() {
+        return Err("Cannot display binary file content".to_string());
+    }
+    
+    // Check file size limits for performance
+    const MAX_FILE_SIZE: usize = 1024 * 1024; // 1MB
+    
+    if blob.size() > MAX_FILE_SIZE {
+        return Err(format!("File too large to display ({}KB > 1MB)", blob.size() / 1024));
+    }
+    
+    // Get file content
+    let content = String::from_utf8(blob.content().to_vec()).map_err(|e| format!("File is not valid UTF-8: {}", e))?;
+    
+    Ok(content)
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+  tauri::Builder::default()
+    .plugin(tauri_plugin_dialog::init())
+    .manage(RepoSessions::default())
+    .manage(BlameStreams::default())
+    .invoke_handler(tauri::generate_handler![get_git_branches, get_git_branches_from_path, create_branch, rename_branch, delete_branch, checkout_branch, checkout_commit, get_git_remotes_from_path, get_remote_details, add_remote, rename_remote, remove_remote, set_remote_url, set_remote_push_url, set_remote_refspecs, get_tags, get_tags_containing_commit, create_tag, delete_tag, push_tag, delete_remote_tag, fetch_remote, pull, push, get_commits_from_path, get_commits_page, get_commits_for_revspecs, get_commit_graph, get_file_history, get_commit_changes, get_commit_stats, get_commit_details, get_file_diff, get_combined_commit_changes, get_combined_file_diff, compare_revisions, get_comparison_file_diff, open_repo_dialog, global_search, get_file_blame, get_file_blame_range, stream_file_blame, cancel_blame_stream, blame_line_at_parent, get_commit_file_tree, get_file_content, open_file_in_editor, get_staged_changes, get_staged_file_diff, get_working_tree_changes, get_working_tree_file_diff, stage_file, unstage_file, stage_hunk, unstage_hunk, stage_lines, unstage_lines, prepare_discard, discard_changes, create_commit, get_stashes, get_stash_diff, get_stash_file_diff, get_stash_changes, get_stash_part_file_diff, stash_push, stash_apply, stash_pop, stash_drop, stash_branch, close_repository])
+    .setup(|app| {
+      if cfg!(debug_assertions) {
+        app.handle().plugin(
+          tauri_plugin_log::Builder::default()
+            .level(log::LevelFilter::Info)
+            .build(),
+        )?;
+      }
+      Ok(())
+    })
+    .on_menu_event(|app, event| {
+      match event.id().as_ref() {
+        "open_repo" => {
+          let _ = app.emit("menu-open-repo", ());
+        }
+        _ => {}
+      }
+    })
+    .menu(|app| {
+      #[cfg(target_os = "macos")]
+      {
+        use tauri::menu::*;
+        
+        let app_menu = SubmenuBuilder::new(app, "Git Viewer")
+          .about(None)
+          .separator()
+          .quit()
+          .build()?;
+          
+        let file_menu = SubmenuBuilder::new(app, "File")
+          .item(&MenuItemBuilder::with_id("open_repo", "Open Repository...")
+            .accelerator("Cmd+O")
+            .build(app)?)
+          .separator()
+          .close_window()
+          .build()?;
+          
+        MenuBuilder::new(app)
+          .item(&app_menu)
+          .item(&file_menu)
+          .build()
+      }
+      
+      #[cfg(not(target_os = "macos"))]
+      {
+        use tauri::menu::*;
+        
+        let file_menu = SubmenuBuilder::new(app, "File")
+          .item(&MenuItemBuilder::with_id("open_repo", "Open Repository...")
+            .accelerator("Ctrl+O")
+            .build(app)?)
+          .separator()
+          .quit()
+          .build()?;
+          
+        MenuBuilder::new(app)
+          .item(&file_menu)
+          .build()
+      }
+    })
+    .run(tauri::generate_context!())
+    .expect("error while running tauri application");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    /// Mock app with its own session cache, so every test starts cold.
+    fn test_app() -> tauri::App<tauri::test::MockRuntime> {
+        let app = tauri::test::mock_app();
+        app.manage(RepoSessions::default());
+        app
+    }
+
+    fn create_test_git_repo() -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let repo_path = temp_dir.path();
+
+        // Initialize git repo
+        Command::new("git")
+            .args(&["init"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to init git repo");
+
+        // Configure git user for test repo
+        Command::new("git")
+            .args(&["config", "user.name", "Test User"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to configure git user name");
+
+        Command::new("git")
+            .args(&["config", "user.email", "test@example.com"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to configure git user email");
+
+        // Create initial commit
+        fs::write(repo_path.join("README.md"), "# Test Repo").expect("Failed to create README");
+        Command::new("git")
+            .args(&["add", "README.md"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to add README");
+
+        Command::new("git")
+            .args(&["commit", "-m", "Initial commit"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to create initial commit");
+
+        temp_dir
+    }
+
+    fn run_git(repo_path: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to run git");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn commit_file(repo_path: &Path, file_name: &str, content: &str, message: &str) -> String {
+        if let Some(parent) = repo_path.join(file_name).parent() {
+            fs::create_dir_all(parent).expect("Failed to create directory");
+        }
+        fs::write(repo_path.join(file_name), content).expect("Failed to write file");
+        run_git(repo_path, &["add", file_name]);
+        run_git(repo_path, &["commit", "-m", message]);
+        run_git(repo_path, &["rev-parse", "HEAD"])
+    }
+
+    #[test]
+    fn test_get_git_branches_from_path_main_branch() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path().to_string_lossy().to_string();
+
+        let result = get_git_branches_from_path(app.state(), repo_path);
+        assert!(result.is_ok());
+
+        let branches = result.unwrap();
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches[0].name, "main");
+        assert!(branches[0].is_current);
+    }
+
+    #[test]
+    fn test_get_git_branches_from_path_multiple_branches() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+
+        // Create a new branch
+        Command::new("git")
+            .args(&["checkout", "-b", "feature-branch"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to create feature branch");
+
+        // Switch back to main
+        Command::new("git")
+            .args(&["checkout", "main"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to switch to main");
+
+        let result = get_git_branches_from_path(app.state(), repo_path.to_string_lossy().to_string());
+        assert!(result.is_ok());
+
+        let branches = result.unwrap();
+        assert_eq!(branches.len(), 2);
+
+        // Find main and feature branches
+        let main_branch = branches.iter().find(|b| b.name == "main").unwrap();
+        let feature_branch = branches.iter().find(|b| b.name == "feature-branch").unwrap();
+
+        assert!(main_branch.is_current);
+        assert!(!feature_branch.is_current);
+    }
+
+    #[test]
+    fn test_get_git_branches_from_path_invalid_path() {
+        let app = test_app();
+        let result = get_git_branches_from_path(app.state(), "/invalid/path".to_string());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_git_branches_from_path_non_git_directory() {
+        let app = test_app();
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let non_git_path = temp_dir.path().to_string_lossy().to_string();
+
+        let result = get_git_branches_from_path(app.state(), non_git_path);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_git_branches_with_upstream() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let remote_dir = TempDir::new().expect("Failed to create temp directory");
+        run_git(remote_dir.path(), &["init", "--bare"]);
+        run_git(repo_path, &["remote", "add", "origin", remote_dir.path().to_str().unwrap()]);
+
+        commit_file(repo_path, "a.txt", "1\n", "Add a");
+        run_git(repo_path, &["push", "-u", "origin", "main"]);
+        run_git(repo_path, &["remote", "set-head", "origin", "main"]);
+        run_git(repo_path, &["reset", "--hard", "HEAD~1"]);
+        commit_file(repo_path, "b.txt", "1\n", "Add b");
+        commit_file(repo_path, "c.txt", "1\n", "Add c");
+        run_git(repo_path, &["branch", "feature"]);
+
+        let branches = get_git_branches_from_path(app.state(), path).unwrap();
+        let names: Vec<&str> = branches.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names.len(), 3);
+        assert!(!names.contains(&"origin/HEAD"));
+
+        let main = branches.iter().find(|b| b.name == "main").unwrap();
+        assert_eq!(main.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((main.ahead, main.behind), (2, 1));
+        assert!(!main.is_remote);
+
+        let feature = branches.iter().find(|b| b.name == "feature").unwrap();
+        assert!(feature.upstream.is_none());
+        assert_eq!((feature.ahead, feature.behind), (0, 0));
+
+        let remote = branches.last().unwrap();
+        assert_eq!(remote.name, "origin/main");
+        assert!(remote.is_remote);
+        assert!(!remote.is_current);
+    }
+
+    #[test]
+    fn test_branch_create_rename_delete() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let first = run_git(repo_path, &["rev-parse", "HEAD"]);
+
+        create_branch(app.state(), path.clone(), "topic".to_string(), first.clone(), None).unwrap();
+        assert!(create_branch(app.state(), path.clone(), "topic".to_string(), first.clone(), None).is_err());
+        assert!(create_branch(app.state(), path.clone(), "bad name".to_string(), first, None).is_err());
+
+        rename_branch(app.state(), path.clone(), "topic".to_string(), "feature".to_string(), None).unwrap();
+        assert!(rename_branch(app.state(), path.clone(), "topic".to_string(), "other".to_string(), None).is_err());
+        assert!(delete_branch(app.state(), path.clone(), "main".to_string(), None).is_err());
+
+        checkout_branch(app.state(), path.clone(), "feature".to_string()).unwrap();
+        commit_file(repo_path, "a.txt", "1\n", "Add a");
+        checkout_branch(app.state(), path.clone(), "main".to_string()).unwrap();
+        assert!(!repo_path.join("a.txt").exists());
+
+        let error = delete_branch(app.state(), path.clone(), "feature".to_string(), None).unwrap_err();
+        assert!(error.contains("not fully merged"));
+        delete_branch(app.state(), path, "feature".to_string(), Some(true)).unwrap();
+        assert_eq!(run_git(repo_path, &["branch", "--list"]), "* main");
+    }
+
+    #[test]
+    fn test_checkout_protects_local_changes() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let first = commit_file(repo_path, "a.txt", "1\n", "Add a");
+        commit_file(repo_path, "a.txt", "2\n", "Change a");
+        fs::write(repo_path.join("a.txt"), "dirty\n").expect("Failed to write file");
+
+        let error = checkout_commit(app.state(), path.clone(), first.clone()).unwrap_err();
+        assert!(error.contains("local changes"));
+        assert_eq!(fs::read_to_string(repo_path.join("a.txt")).unwrap(), "dirty\n");
+
+        // Changes to files that don't differ between the commits are carried over
+        run_git(repo_path, &["checkout", "a.txt"]);
+        fs::write(repo_path.join("README.md"), "changed").expect("Failed to write file");
+        checkout_commit(app.state(), path.clone(), first.clone()).unwrap();
+        assert_eq!(run_git(repo_path, &["rev-parse", "HEAD"]), first);
+        assert_eq!(run_git(repo_path, &["symbolic-ref", "-q", "HEAD"]), "");
+        assert_eq!(fs::read_to_string(repo_path.join("README.md")).unwrap(), "changed");
+
+        // A new branch is not left behind when checking it out fails
+        run_git(repo_path, &["checkout", "-q", "main"]);
+        fs::write(repo_path.join("a.txt"), "dirty\n").expect("Failed to write file");
+        assert!(create_branch(app.state(), path.clone(), "fails".to_string(), first, Some(true)).is_err());
+        assert_eq!(run_git(repo_path, &["branch", "--list", "fails"]), "");
+    }
+
+    #[test]
+    fn test_checkout_remote_branch_creates_tracking_branch() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let remote_dir = TempDir::new().expect("Failed to create temp directory");
+        run_git(remote_dir.path(), &["init", "--bare"]);
+        run_git(repo_path, &["remote", "add", "origin", remote_dir.path().to_str().unwrap()]);
+        run_git(repo_path, &["push", "origin", "main:release"]);
+        run_git(repo_path, &["push", "origin", "main:hotfix"]);
+        run_git(repo_path, &["fetch", "origin"]);
+
+        checkout_branch(app.state(), path.clone(), "origin/release".to_string()).unwrap();
+        assert_eq!(run_git(repo_path, &["rev-parse", "--abbrev-ref", "HEAD"]), "release");
+        assert_eq!(run_git(repo_path, &["rev-parse", "--abbrev-ref", "release@{upstream}"]), "origin/release");
+
+        // The tracking branch is removed again when the checkout fails
+        run_git(repo_path, &["checkout", "-q", "main"]);
+        commit_file(repo_path, "README.md", "# Changed", "Change readme");
+        fs::write(repo_path.join("README.md"), "dirty").expect("Failed to write file");
+        assert!(checkout_branch(app.state(), path, "origin/hotfix".to_string()).is_err());
+        assert_eq!(run_git(repo_path, &["branch", "--list", "hotfix"]), "");
+    }
+
+    #[test]
+    fn test_remote_management() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let added = add_remote(app.state(), path.clone(), "origin".to_string(), "https://example.com/repo.git".to_string()).unwrap();
+        assert_eq!(added.fetch_refspecs, vec!["+refs/heads/*:refs/remotes/origin/*"]);
+        assert!(added.push_url.is_none());
+        assert!(add_remote(app.state(), path.clone(), "origin".to_string(), "https://example.com/other.git".to_string()).is_err());
+        assert!(add_remote(app.state(), path.clone(), "bad name".to_string(), "https://example.com/repo.git".to_string()).is_err());
+
+        run_git(repo_path, &["config", "branch.main.remote", "origin"]);
+        run_git(repo_path, &["config", "branch.main.merge", "refs/heads/main"]);
+
+        let renamed = rename_remote(app.state(), path.clone(), "origin".to_string(), "upstream".to_string()).unwrap();
+        assert_eq!(renamed.tracking_branches, vec!["main"]);
+        assert_eq!(renamed.fetch_refspecs, vec!["+refs/heads/*:refs/remotes/upstream/*"]);
+
+        let updated = set_remote_push_url(app.state(), path.clone(), "upstream".to_string(), Some("git@example.com:me/repo.git".to_string())).unwrap();
+        assert_eq!(updated.push_url.as_deref(), Some("git@example.com:me/repo.git"));
+        let updated = set_remote_push_url(app.state(), path.clone(), "upstream".to_string(), None).unwrap();
+        assert!(updated.push_url.is_none());
+        assert!(set_remote_url(app.state(), path.clone(), "upstream".to_string(), "ftp://example.com/repo".to_string()).is_err());
+
+        let updated = set_remote_refspecs(app.state(), path.clone(), "upstream".to_string(), vec!["+refs/heads/main:refs/remotes/upstream/main".to_string()], vec!["refs/heads/main:refs/heads/main".to_string()]).unwrap();
+        assert_eq!(updated.fetch_refspecs, vec!["+refs/heads/main:refs/remotes/upstream/main"]);
+        assert_eq!(updated.push_refspecs, vec!["refs/heads/main:refs/heads/main"]);
+        assert!(set_remote_refspecs(app.state(), path.clone(), "upstream".to_string(), vec!["refs/heads/*:refs/a/b*c*".to_string()], Vec::new()).is_err());
+        let unchanged = get_remote_details(app.state(), path.clone(), "upstream".to_string()).unwrap();
+        assert_eq!(unchanged.fetch_refspecs, updated.fetch_refspecs);
+        assert_eq!(unchanged.push_refspecs, updated.push_refspecs);
+
+        assert_eq!(remove_remote(app.state(), path.clone(), "upstream".to_string()).unwrap(), vec!["main"]);
+        assert!(get_remote_details(app.state(), path, "upstream".to_string()).is_err());
+        assert_eq!(run_git(repo_path, &["config", "branch.main.remote"]), "");
+    }
+
+    #[test]
+    fn test_validate_remote_url() {
+        let temp_repo = create_test_git_repo();
+        let repo = git2::Repository::open(temp_repo.path()).expect("Failed to open repository");
+        assert!(validate_remote_url(&repo, "https://github.com/owner/repo.git").is_ok());
+        assert!(validate_remote_url(&repo, "ssh://git@host:2222/repo.git").is_ok());
+        assert!(validate_remote_url(&repo, "git@github.com:owner/repo.git").is_ok());
+        assert!(validate_remote_url(&repo, env::temp_dir().to_str().unwrap()).is_ok());
+        assert!(validate_remote_url(&repo, "").is_err());
+        assert!(validate_remote_url(&repo, "https://").is_err());
+        assert!(validate_remote_url(&repo, "ftp://host/repo").is_err());
+        assert!(validate_remote_url(&repo, "https://host/my repo").is_err());
+        assert!(validate_remote_url(&repo, "/no/such/repository").is_err());
+
+        // Relative paths are resolved against the repository, not the process
+        fs::create_dir_all(temp_repo.path().join("vendor/lib")).expect("Failed to create directory");
+        assert!(validate_remote_url(&repo, "vendor/lib").is_ok());
+        assert!(validate_remote_url(&repo, "src").is_err());
+
+        let bare_dir = TempDir::new().expect("Failed to create temp directory");
+        let bare = git2::Repository::init_bare(bare_dir.path()).expect("Failed to init repository");
+        assert!(validate_remote_url(&bare, "refs").is_ok());
+        assert!(validate_remote_url(&bare, "vendor/lib").is_err());
+    }
+
+    #[test]
+    fn test_get_tags() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let first = run_git(repo_path, &["rev-parse", "HEAD"]);
+        run_git(repo_path, &["tag", "v1.9"]);
+        let second = commit_file(repo_path, "a.txt", "1\n", "Add a");
+        run_git(repo_path, &["tag", "-a", "v1.10", "-m", "Release 1.10"]);
+        commit_file(repo_path, "a.txt", "2\n", "Change a");
+
+        let tags = get_tags(app.state(), path.clone(), None).unwrap();
+        let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["v1.10", "v1.9"]);
+        assert!(tags[0].is_annotated);
+        assert_eq!(tags[0].target_id, second);
+        assert_eq!(tags[0].message.as_deref(), Some("Release 1.10"));
+        assert!(tags[0].tagger.as_deref().unwrap().contains("Test User"));
+        assert!(!tags[1].is_annotated);
+        assert_eq!(tags[1].target_id, first);
+        assert!(tags[1].tagger.is_none());
+        assert!(get_tags(app.state(), path.clone(), Some("size".to_string())).is_err());
+
+        assert_eq!(get_tags_containing_commit(app.state(), path.clone(), first).unwrap(), vec!["v1.9", "v1.10"]);
+        assert_eq!(get_tags_containing_commit(app.state(), path.clone(), second).unwrap(), vec!["v1.10"]);
+        let head = run_git(repo_path, &["rev-parse", "HEAD"]);
+        assert!(get_tags_containing_commit(app.state(), path, head).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_create_and_delete_tags() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let head = run_git(repo_path, &["rev-parse", "HEAD"]);
+
+        let light = create_tag(app.state(), path.clone(), "v1".to_string(), head.clone(), None, None, None).unwrap();
+        assert!(!light.is_annotated);
+        assert_eq!(light.target_id, head);
+        assert!(create_tag(app.state(), path.clone(), "v1".to_string(), head.clone(), None, None, None).is_err());
+        assert!(create_tag(app.state(), path.clone(), "bad..name".to_string(), head.clone(), None, None, None).is_err());
+
+        let annotated = create_tag(app.state(), path.clone(), "v2".to_string(), head.clone(), Some("Release 2".to_string()), None, None).unwrap();
+        assert!(annotated.is_annotated);
+        assert_eq!(run_git(repo_path, &["cat-file", "-t", "v2"]), "tag");
+        assert_eq!(annotated.message.as_deref(), Some("Release 2"));
+
+        delete_tag(app.state(), path.clone(), "v1".to_string()).unwrap();
+        assert!(delete_tag(app.state(), path.clone(), "v1".to_string()).is_err());
+        assert_eq!(run_git(repo_path, &["tag", "-l"]), "v2");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_signed_tag() {
+        let app = test_app();
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let head = run_git(repo_path, &["rev-parse", "HEAD"]);
+
+        let gpg = repo_path.join(".git").join("fake-gpg");
+        fs::write(&gpg, "#!/bin/sh\ncat > /dev/null\nprintf -- '-----BEGIN PGP SIGNATURE-----\\nfake\\n-----END PGP SIGNATURE-----\\n'\n")
+            .expect("Failed to write gpg script");
+        fs::set_permissions(&gpg, fs::Permissions::from_mode(0o755)).expect("Failed to make gpg script executable");
+        run_git(repo_path, &["config", "gpg.program", gpg.to_str().unwrap()]);
+
+        assert!(create_tag(app.state(), path.clone(), "v1".to_string(), head.clone(), None, Some(true), None).is_err());
+        let tag = create_tag(app.state(), path, "v1".to_string(), head.clone(), Some("Signed".to_string()), Some(true), None).unwrap();
+        assert!(tag.is_annotated);
+        assert_eq!(tag.target_id, head);
+        assert_eq!(tag.message.as_deref(), Some("Signed"));
+
+        let raw = run_git(repo_path, &["cat-file", "-p", "v1"]);
+        assert!(raw.contains("tagger Test User <test@example.com>"));
+        assert!(raw.ends_with("-----END PGP SIGNATURE-----"));
+        assert_eq!(run_git(repo_path, &["rev-parse", "v1^{commit}"]), head);
+    }
+
+    #[test]
+    fn test_push_and_delete_remote_tag() {
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let remote_dir = TempDir::new().expect("Failed to create temp directory");
+        run_git(remote_dir.path(), &["init", "--bare"]);
+        run_git(repo_path, &["remote", "add", "origin", remote_dir.path().to_str().unwrap()]);
+        run_git(repo_path, &["tag", "v1"]);
+
+        push_tag(path.clone(), "origin".to_string(), "v1".to_string()).unwrap();
+        assert_eq!(run_git(remote_dir.path(), &["tag", "-l"]), "v1");
+        assert!(push_tag(path.clone(), "origin".to_string(), "missing".to_string()).is_err());
+        assert!(push_tag(path.clone(), "upstream".to_string(), "v1".to_string()).is_err());
+
+        delete_remote_tag(path, "origin".to_string(), "v1".to_string()).unwrap();
+        assert_eq!(run_git(remote_dir.path(), &["tag", "-l"]), "");
+    }
+
+    #[test]
+    fn test_fetch_pull_and_push() {
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let remote_dir = TempDir::new().expect("Failed to create temp directory");
+        run_git(remote_dir.path(), &["init", "--bare"]);
+        let remote_url = remote_dir.path().to_str().unwrap();
+        run_git(repo_path, &["remote", "add", "origin", remote_url]);
+
+        // First push sets up tracking
+        let handle = RepoSessions::default().open(&path).unwrap();
+        let mut progress = Vec::new();
+        push_branch(&handle.lock(), None, None, false, true, &mut |p| progress.push(p)).unwrap();
+        assert_eq!(run_git(remote_dir.path(), &["rev-parse", "main"]), run_git(repo_path, &["rev-parse", "HEAD"]));
+        assert_eq!(run_git(repo_path, &["rev-parse", "--abbrev-ref", "main@{upstream}"]), "origin/main");
+        assert!(progress.iter().all(|p| p.operation == "push" && p.remote == "origin"));
+
+        // Another clone pushes new work and a branch that is later removed
+        let other_dir = TempDir::new().expect("Failed to create temp directory");
+        run_git(other_dir.path(), &["clone", remote_url, "."]);
+        run_git(other_dir.path(), &["config", "user.name", "Other User"]);
+        run_git(other_dir.path(), &["config", "user.email", "other@example.com"]);
+        let upstream = commit_file(other_dir.path(), "a.txt", "1\n", "Add a");
+        run_git(other_dir.path(), &["push", "origin", "main", "main:old"]);
+
+        fetch_from_remote(&handle.lock(), "origin", false, &mut |_| {}).unwrap();
+        assert_eq!(run_git(repo_path, &["rev-parse", "origin/main"]), upstream);
+        run_git(remote_dir.path(), &["branch", "-D", "old"]);
+        fetch_from_remote(&handle.lock(), "origin", true, &mut |_| {}).unwrap();
+        assert_eq!(run_git(repo_path, &["branch", "-r"]), "origin/main");
+        assert!(fetch_from_remote(&handle.lock(), "missing", false, &mut |_| {}).is_err());
+
+        fetch_upstream(&handle.lock(), &mut |_| {}).unwrap();
+        let result = fast_forward_to_upstream(&handle.lock()).unwrap();
+        assert_eq!(result.status, "fast-forward");
+        assert_eq!(result.commit_id, upstream);
+        assert_eq!(run_git(repo_path, &["rev-parse", "HEAD"]), upstream);
+        assert_eq!(fs::read_to_string(repo_path.join("a.txt")).unwrap(), "1\n");
+        assert_eq!(fast_forward_to_upstream(&handle.lock()).unwrap().status, "up-to-date");
+
+        // Diverged histories are neither pulled nor pushed without force
+        commit_file(other_dir.path(), "b.txt", "1\n", "Add b");
+        run_git(other_dir.path(), &["push", "origin", "main"]);
+        let local = commit_file(repo_path, "c.txt", "1\n", "Add c");
+        fetch_upstream(&handle.lock(), &mut |_| {}).unwrap();
+        assert!(fast_forward_to_upstream(&handle.lock()).unwrap_err().contains("diverged"));
+        assert!(push_branch(&handle.lock(), None, None, false, false, &mut |_| {}).is_err());
+        push_branch(&handle.lock(), Some("origin"), Some("main"), true, false, &mut |_| {}).unwrap();
+        assert_eq!(run_git(remote_dir.path(), &["rev-parse", "main"]), local);
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("v1.10.0", "v1.9.3"), std::cmp::Ordering::Greater);
+        assert_eq!(compare_versions("v2.0", "v2.0.1"), std::cmp::Ordering::Less);
+        assert_eq!(compare_versions("v01", "v1"), std::cmp::Ordering::Equal);
+        assert_eq!(compare_versions("alpha", "beta"), std::cmp::Ordering::Less);
+    }
+
+    #[test]
+    fn test_get_commit_graph() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let root = run_git(repo_path, &["rev-parse", "HEAD"]);
+
+        run_git(repo_path, &["checkout", "-b", "feature"]);
+        let feature = commit_file(repo_path, "f.txt", "1\n", "Feature work");
+        run_git(repo_path, &["checkout", "main"]);
+        let main = commit_file(repo_path, "m.txt", "1\n", "Main work");
+        run_git(repo_path, &["merge", "--no-ff", "feature", "-m", "Merge feature"]);
+        let merge = run_git(repo_path, &["rev-parse", "HEAD"]);
+        run_git(repo_path, &["checkout", "-b", "topic", &main]);
+        let topic = commit_file(repo_path, "t.txt", "1\n", "Topic work");
+
+        let graph = get_commit_graph(app.state(), path.clone(), vec!["main".to_string(), "topic".to_string()], None, None).unwrap();
+        assert_eq!(graph.rows.len(), 5);
+        assert!((2..=3).contains(&graph.lane_count));
+        assert!(!graph.has_more);
+
+        let row = |id: &str| graph.rows.iter().position(|r| r.commit.id == id).unwrap();
+        assert!(row(&merge) < row(&main) && row(&merge) < row(&feature) && row(&topic) < row(&main));
+        assert_eq!(graph.rows[row(&root)].commit.id, graph.rows.last().unwrap().commit.id);
+
+        let merge_row = &graph.rows[row(&merge)];
+        assert_eq!(merge_row.parents, vec![main.clone(), feature.clone()]);
+        let parent_lane = |edge_type: &str| merge_row.edges.iter().find(|e| e.edge_type == edge_type && e.from_lane == merge_row.lane).unwrap().to_lane;
+        assert_ne!(parent_lane("parent"), parent_lane("merge"));
+        assert!(graph.rows.last().unwrap().edges.is_empty());
+
+        // Every line leaving a row must arrive at a lane that is in use in the next row
+        for pair in graph.rows.windows(2) {
+            for edge in &pair[0].edges {
+                let next = &pair[1];
+                assert!(edge.to_lane == next.lane || next.edges.iter().any(|e| e.from_lane == edge.to_lane));
+            }
+        }
+
+        // Paging keeps the lanes of the full layout
+        let first = get_commit_graph(app.state(), path.clone(), vec!["main".to_string(), "topic".to_string()], None, Some(2)).unwrap();
+        assert!(first.has_more);
+        let rest = get_commit_graph(app.state(), path.clone(), vec!["main".to_string(), "topic".to_string()], first.next_cursor, Some(10)).unwrap();
+        let lanes = |rows: &[GraphRow]| rows.iter().map(|r| (r.commit.id.clone(), r.lane, r.edges.len())).collect::<Vec<_>>();
+        let paged: Vec<_> = lanes(&first.rows).into_iter().chain(lanes(&rest.rows)).collect();
+        assert_eq!(paged, lanes(&graph.rows));
+
+        // Laying out earlier pages again gives the same lanes as resuming
+        let fresh = test_app();
+        let replayed = get_commit_graph(fresh.state(), path.clone(), vec!["main".to_string(), "topic".to_string()], Some(first.rows[1].commit.id.clone()), Some(10)).unwrap();
+        assert_eq!(lanes(&replayed.rows), lanes(&rest.rows));
+
+        assert!(get_commit_graph(app.state(), path, vec!["missing".to_string()], None, None).is_err());
+    }
+
+    #[test]
+    fn test_get_commits_from_path() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path().to_string_lossy().to_string();
+
+        let result = get_commits_from_path(app.state(), repo_path, "main".to_string());
+        assert!(result.is_ok());
+
+        let commits = result.unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].message, "Initial commit");
+        assert!(commits[0].author.contains("Test User"));
+        assert!(commits[0].short_id.len() == 8);
+    }
+
+    #[test]
+    fn test_get_commits_from_path_invalid_branch() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path().to_string_lossy().to_string();
+
+        let result = get_commits_from_path(app.state(), repo_path, "nonexistent-branch".to_string());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_commits_for_revspecs() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let root = run_git(repo_path, &["rev-parse", "HEAD"]);
+
+        run_git(repo_path, &["checkout", "-b", "feature"]);
+        let feature = commit_file(repo_path, "f.txt", "1\n", "Feature work");
+        run_git(repo_path, &["checkout", "main"]);
+        let main = commit_file(repo_path, "m.txt", "1\n", "Main work");
+        run_git(repo_path, &["tag", "v1", &root]);
+
+        let ids = |specs: &[&str]| {
+            let specs = specs.iter().map(|spec| spec.to_string()).collect();
+            let mut ids: Vec<String> = get_commits_for_revspecs(app.state(), path.clone(), specs, None, None, None)
+                .unwrap()
+                .commits
+                .into_iter()
+                .map(|commit| commit.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        let sorted = |mut ids: Vec<&String>| {
+            ids.sort();
+            ids.into_iter().cloned().collect::<Vec<String>>()
+        };
+
+        assert_eq!(ids(&["--all"]), sorted(vec![&root, &feature, &main]));
+        assert_eq!(ids(&["main", "feature"]), sorted(vec![&root, &feature, &main]));
+        assert_eq!(ids(&["main..feature"]), vec![feature.clone()]);
+        assert_eq!(ids(&["feature", "^main"]), vec![feature.clone()]);
+        assert_eq!(ids(&["..feature"]), vec![feature.clone()]);
+        assert_eq!(ids(&["main...feature"]), sorted(vec![&feature, &main]));
+        assert_eq!(ids(&["--tags"]), vec![root.clone()]);
+        assert_eq!(ids(&[]), sorted(vec![&root, &main]));
+
+        let error = get_commits_for_revspecs(app.state(), path.clone(), vec!["nope".to_string()], None, None, None).unwrap_err();
+        assert!(error.contains("Unknown revision 'nope'"));
+        assert!(get_commits_for_revspecs(app.state(), path.clone(), vec!["main..nope".to_string()], None, None, None).is_err());
+
+        // A criss-cross merge leaves two merge bases, both shared by the sides
+        run_git(repo_path, &["checkout", "-q", "-b", "left", "main"]);
+        let left = commit_file(repo_path, "l.txt", "1\n", "Left work");
+        run_git(repo_path, &["checkout", "-q", "-b", "right", "main"]);
+        let right = commit_file(repo_path, "r.txt", "1\n", "Right work");
+        run_git(repo_path, &["merge", "-q", "--no-ff", "-m", "Merge left", &left]);
+        run_git(repo_path, &["checkout", "-q", "left"]);
+        run_git(repo_path, &["merge", "-q", "--no-ff", "-m", "Merge right", &right]);
+        commit_file(repo_path, "l.txt", "2\n", "More left work");
+        let mut expected: Vec<String> = run_git(repo_path, &["rev-list", "left...right"]).lines().map(String::from).collect();
+        expected.sort();
+        assert_eq!(expected.len(), 3);
+        assert_eq!(ids(&["left...right"]), expected);
+    }
+
+    #[test]
+    fn test_get_commits_with_filter() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        for i in 0..60 {
+            commit_file(repo_path, "a.txt", &format!("{}\n", i), &format!("Change {}", i));
+        }
+        run_git(repo_path, &["config", "user.name", "Jane Roe"]);
+        run_git(repo_path, &["config", "user.email", "jane@example.org"]);
+        let fix = commit_file(repo_path, "b.txt", "1\n", "Fix crash in parser\n\nFixes: #12");
+        let fix_time: i64 = run_git(repo_path, &["log", "-1", "--format=%ct"]).parse().unwrap();
+
+        let filtered = |filter: CommitFilter| {
+            get_commits_for_revspecs(app.state(), path.clone(), vec!["main".to_string()], None, Some(50), Some(filter)).unwrap()
+        };
+
+        let by_author = filtered(CommitFilter { author: Some("JANE".to_string()), ..Default::default() });
+        assert_eq!(by_author.commits.len(), 1);
+        assert_eq!(by_author.commits[0].id, fix);
+        assert!(!by_author.has_more);
+
+        // The root commit lies beyond the first page of unfiltered history
+        let by_message = filtered(CommitFilter { message: Some("^Initial".to_string()), ..Default::default() });
+        assert_eq!(by_message.commits.len(), 1);
+        assert_eq!(by_message.commits[0].message, "Initial commit");
+        let by_body = filtered(CommitFilter { message: Some("Fixes: #\\d+".to_string()), ..Default::default() });
+        assert_eq!(by_body.commits[0].id, fix);
+
+        let by_committer = filtered(CommitFilter { committer: Some("test@example\\.com".to_string()), ..Default::default() });
+        assert_eq!(by_committer.commits.len(), 50);
+        assert!(by_committer.has_more);
+        assert!(by_committer.commits.iter().all(|commit| commit.id != fix));
+
+        let future = filtered(CommitFilter { since: Some(fix_time + 1), ..Default::default() });
+        assert!(future.commits.is_empty());
+        let window = filtered(CommitFilter { since: Some(fix_time), until: Some(fix_time), author: Some("jane".to_string()), ..Default::default() });
+        assert_eq!(window.commits.len(), 1);
+
+        let invalid = CommitFilter { message: Some("(".to_string()), ..Default::default() };
+        assert!(get_commits_for_revspecs(app.state(), path, Vec::new(), None, None, Some(invalid)).is_err());
+    }
+
+    #[test]
+    fn test_get_file_history_follows_renames() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let content = "one\ntwo\nthree\nfour\nfive\nsix\n";
+        let added = commit_file(repo_path, "src/a.txt", content, "Add a");
+        let modified = commit_file(repo_path, "src/a.txt", &format!("{}seven\n", content), "Extend a");
+        commit_file(repo_path, "other.txt", "x\n", "Unrelated");
+        run_git(repo_path, &["mv", "src/a.txt", "src/b.txt"]);
+        run_git(repo_path, &["commit", "-m", "Rename a to b"]);
+        let renamed = run_git(repo_path, &["rev-parse", "HEAD"]);
+        let edited = commit_file(repo_path, "src/b.txt", &format!("zero\n{}seven\n", content), "Edit b");
+
+        let history = get_file_history(app.state(), path.clone(), "src/b.txt".to_string(), None, None, None, None).unwrap();
+        let ids: Vec<&str> = history.entries.iter().map(|e| e.commit.id.as_str()).collect();
+        assert_eq!(ids, vec![edited.as_str(), renamed.as_str(), modified.as_str(), added.as_str()]);
+
+        let paths: Vec<&str> = history.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/b.txt", "src/b.txt", "src/a.txt", "src/a.txt"]);
+        assert_eq!(history.entries[1].status, "renamed");
+        assert_eq!(history.entries[1].old_path.as_deref(), Some("src/a.txt"));
+        assert_eq!((history.entries[0].additions, history.entries[0].deletions), (1, 0));
+        assert_eq!(history.entries[3].status, "added");
+        assert_eq!((history.entries[3].additions, history.entries[3].deletions), (6, 0));
+
+        // Paging keeps following the rename
+        let first = get_file_history(app.state(), path.clone(), "src/b.txt".to_string(), None, None, None, Some(2)).unwrap();
+        assert!(first.has_more);
+        assert_eq!(first.next_path.as_deref(), Some("src/a.txt"));
+        let fresh = test_app();
+        let replayed = get_file_history(fresh.state(), path.clone(), "src/b.txt".to_string(), None, first.next_cursor.clone(), first.next_path.clone(), Some(2)).unwrap();
+        let rest = get_file_history(app.state(), path.clone(), "src/b.txt".to_string(), None, first.next_cursor, first.next_path, Some(2)).unwrap();
+        assert_eq!(replayed.entries.len(), rest.entries.len());
+        let rest_ids: Vec<&str> = rest.entries.iter().map(|e| e.commit.id.as_str()).collect();
+        assert_eq!(rest_ids, vec![modified.as_str(), added.as_str()]);
+        assert!(!rest.has_more);
+        assert!(rest.next_path.is_none());
+
+        // Directories collect every change below them, without following renames
+        let directory = get_file_history(app.state(), path.clone(), "src/".to_string(), None, None, None, None).unwrap();
+        assert_eq!(directory.entries.len(), 4);
+        assert_eq!(directory.entries[1].commit.id, renamed);
+        assert_eq!(directory.entries[1].path, "src");
+
+        let from_older = get_file_history(app.state(), path, "src/a.txt".to_string(), Some(modified.clone()), None, None, None).unwrap();
+        assert_eq!(from_older.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_get_commits_page_walks_full_history() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+
+        for i in 1..=4 {
+            commit_file(repo_path, &format!("page{}.txt", i), &format!("page {}", i), &format!("Page commit {}", i));
+        }
+
+        let path = repo_path.to_string_lossy().to_string();
+        let mut seen = Vec::new();
+        let mut after = None;
+        loop {
+            let page = get_commits_page(app.state(), path.clone(), "main".to_string(), after.clone(), Some(2)).unwrap();
+            assert!(page.commits.len() <= 2);
+            seen.extend(page.commits.iter().map(|c| c.id.clone()));
+            if !page.has_more {
+                assert!(page.next_cursor.is_none());
+                break;
+            }
+            after = page.next_cursor;
+        }
+
+        // 4 page commits plus the initial commit, each exactly once
+        assert_eq!(seen.len(), 5);
+        let mut unique = seen.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 5);
+
+        // Without a paused walk, e.g. after a restart, the cursor is found by walking again
+        let fresh = test_app();
+        let page = get_commits_page(fresh.state(), path.clone(), "main".to_string(), Some(seen[1].clone()), Some(2)).unwrap();
+        let ids: Vec<String> = page.commits.iter().map(|c| c.id.clone()).collect();
+        assert_eq!(ids, seen[2..4].to_vec());
+
+        // A paused walk is only resumed for the same cursor
+        let first = get_commits_page(fresh.state(), path.clone(), "main".to_string(), None, Some(2)).unwrap();
+        let again = get_commits_page(fresh.state(), path.clone(), "main".to_string(), Some(seen[3].clone()), Some(2)).unwrap();
+        assert_eq!(again.commits[0].id, seen[4]);
+        let second = get_commits_page(fresh.state(), path, "main".to_string(), first.next_cursor, Some(2)).unwrap();
+        let ids: Vec<String> = second.commits.iter().map(|c| c.id.clone()).collect();
+        assert_eq!(ids, seen[2..4].to_vec());
+    }
+
+    #[test]
+    fn test_get_commits_page_unknown_cursor() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path().to_string_lossy().to_string();
+
+        let result = get_commits_page(app.state(), repo_path.clone(), "main".to_string(), Some("0000000000000000000000000000000000000001".to_string()), None);
+        assert!(result.is_err());
+
+        let result = get_commits_page(app.state(), repo_path, "main".to_string(), Some("not-an-oid".to_string()), None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_repo_sessions_reuse_handle_until_refs_change() {
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let sessions = RepoSessions::default();
+
+        let first = sessions.open(&path).unwrap();
+        let second = sessions.open(&path).unwrap();
+        assert!(Arc::ptr_eq(&first.0, &second.0));
+
+        // A new commit moves the branch ref and rewrites the index
+        let new_head = commit_file(repo_path, "next.txt", "next", "Next commit");
+        let third = sessions.open(&path).unwrap();
+        assert!(!Arc::ptr_eq(&first.0, &third.0));
+        assert_eq!(third.lock().head().unwrap().target().unwrap().to_string(), new_head);
+
+        sessions.close(&path);
+        let fourth = sessions.open(&path).unwrap();
+        assert!(!Arc::ptr_eq(&third.0, &fourth.0));
+    }
+
+    #[test]
+    fn test_repo_sessions_invalid_path() {
+        let sessions = RepoSessions::default();
+        assert!(sessions.open("/invalid/path").is_err());
+    }
+
+    #[test]
+    fn test_get_commit_details() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+        let root = run_git(repo_path, &["rev-parse", "HEAD"]);
+
+        fs::write(repo_path.join("a.txt"), "1\n").expect("Failed to write file");
+        run_git(repo_path, &["add", "a.txt"]);
+        let message = "Add a\n\nLonger explanation\nover two lines.\n\nSigned-off-by: Test User <test@example.com>\nCo-authored-by: Jane Roe <jane@example.org>\n";
+        Command::new("git")
+            .args(["commit", "-m", message, "--date", "2024-03-01T12:30:00+05:30"])
+            .env("GIT_COMMITTER_DATE", "2024-03-01T08:00:00-02:00")
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to commit");
+        let head = run_git(repo_path, &["rev-parse", "HEAD"]);
+        run_git(repo_path, &["tag", "v1"]);
+
+        let details = get_commit_details(app.state(), path.clone(), head.clone()).unwrap();
+        assert_eq!(details.summary, "Add a");
+        assert!(details.body.starts_with("Longer explanation\nover two lines."));
+        assert_eq!(details.trailers.len(), 2);
+        assert_eq!(details.trailers[0].key, "Signed-off-by");
+        assert_eq!(details.trailers[1].key, "Co-authored-by");
+        assert_eq!(details.trailers[1].value, "Jane Roe <jane@example.org>");
+
+        assert_eq!(details.author.email, "test@example.com");
+        assert_eq!(details.author.timezone, "+05:30");
+        assert_eq!(details.author.date, "2024-03-01 12:30:00");
+        assert_eq!(details.committer.timezone, "-02:00");
+        assert_eq!(details.committer.date, "2024-03-01 08:00:00");
+        assert_eq!(details.parent_ids, vec![root.clone()]);
+        assert_eq!(details.tree_id, run_git(repo_path, &["rev-parse", "HEAD^{tree}"]));
+        assert_eq!(details.refs, vec!["HEAD", "main", "v1"]);
+
+        let root_details = get_commit_details(app.state(), path, root).unwrap();
+        assert!(root_details.parent_ids.is_empty());
+        assert!(root_details.trailers.is_empty());
+        assert!(root_details.body.is_empty());
+        assert!(root_details.refs.is_empty());
+    }
+
+    #[test]
+    fn test_merge_commit_diffs() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        commit_file(repo_path, "conflict.txt", "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n", "Add file");
+        run_git(repo_path, &["checkout", "-b", "feature"]);
+        commit_file(repo_path, "conflict.txt", "1\n2f\n3\n4\n5\n6\n7\n8\n9f\n10\n", "Feature edit");
+        commit_file(repo_path, "f.txt", "feature\n", "Feature file");
+        run_git(repo_path, &["checkout", "main"]);
+        commit_file(repo_path, "conflict.txt", "1\n2m\n3\n4\n5\n6\n7\n8\n9\n10\n", "Main edit");
+        run_git(repo_path, &["merge", "feature"]);
+        fs::write(repo_path.join("conflict.txt"), "1\n2r\n3\n4\n5\n6\n7\n8\n9f\n10\n").expect("Failed to write file");
+        run_git(repo_path, &["add", "conflict.txt"]);
+        run_git(repo_path, &["commit", "--no-edit"]);
+        let merge = run_git(repo_path, &["rev-parse", "HEAD"]);
+
+        let paths = |changes: Vec<FileChange>| {
+            let mut paths: Vec<String> = changes.into_iter().map(|c| c.path).collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(paths(get_commit_changes(app.state(), path.clone(), merge.clone(), None).unwrap()), vec!["conflict.txt", "f.txt"]);
+        assert_eq!(paths(get_commit_changes(app.state(), path.clone(), merge.clone(), Some(1)).unwrap()), vec!["conflict.txt"]);
+        assert!(get_commit_changes(app.state(), path.clone(), merge.clone(), Some(2)).is_err());
+        let against_feature = get_commit_stats(app.state(), path.clone(), merge.clone(), Some(1)).unwrap();
+        assert_eq!((against_feature.files_changed, against_feature.insertions, against_feature.deletions), (1, 1, 1));
+        assert_eq!(get_commit_stats(app.state(), path.clone(), merge.clone(), None).unwrap().files_changed, 2);
+        assert_eq!(paths(get_combined_commit_changes(app.state(), path.clone(), merge.clone()).unwrap()), vec!["conflict.txt"]);
+
+        let against_feature = get_file_diff(app.state(), path.clone(), merge.clone(), "conflict.txt".to_string(), Some(1)).unwrap();
+        let added: Vec<&str> = against_feature.diff_lines.iter().filter(|l| l.line_type == "addition").map(|l| l.content.as_str()).collect();
+        assert_eq!(added, vec!["2r"]);
+
+        let dense = get_combined_file_diff(app.state(), path.clone(), merge.clone(), "conflict.txt".to_string(), None).unwrap();
+        assert_eq!(dense.parent_ids.len(), 2);
+        let changed = |diff: &CombinedFileDiff| {
+            diff.diff_lines.iter()
+                .filter(|l| l.line_type != "context")
+                .map(|l| format!("{}{}", l.markers, l.content))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changed(&dense), vec!["- 2m", " -2f", "++2r"]);
+        assert!(dense.diff_lines.iter().all(|l| l.hunk_index == 0));
+        let resolved = dense.diff_lines.iter().find(|l| l.content == "2r").unwrap();
+        assert_eq!(resolved.new_line_number, Some(2));
+
+        let combined = get_combined_file_diff(app.state(), path.clone(), merge.clone(), "conflict.txt".to_string(), Some(false)).unwrap();
+        assert_eq!(changed(&combined), vec!["- 2m", " -2f", "++2r", "- 9", "+ 9f"]);
+
+        assert!(get_combined_file_diff(app.state(), path, merge, "missing.txt".to_string(), None).is_err());
+    }
+
+    #[test]
+    fn test_compare_revisions() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let base = commit_file(repo_path, "shared.txt", "1\n2\n3\n", "Add shared");
+        run_git(repo_path, &["checkout", "-b", "feature"]);
+        commit_file(repo_path, "shared.txt", "1\n2\n3\n4\n", "Extend shared");
+        commit_file(repo_path, "feature.txt", "new\n", "Add feature file");
+        run_git(repo_path, &["checkout", "main"]);
+        commit_file(repo_path, "main.txt", "main\n", "Add main file");
+
+        let paths = |comparison: &RevisionComparison| {
+            let mut paths: Vec<String> = comparison.changes.iter().map(|c| c.path.clone()).collect();
+            paths.sort();
+            paths
+        };
+
+        let two_dot = compare_revisions(app.state(), path.clone(), "main".to_string(), "feature".to_string(), None).unwrap();
+        assert_eq!(paths(&two_dot), vec!["feature.txt", "main.txt", "shared.txt"]);
+        assert!(two_dot.merge_base_id.is_none());
+        let main_file = two_dot.changes.iter().find(|c| c.path == "main.txt").unwrap();
+        assert_eq!(main_file.status, "deleted");
+
+        let three_dot = compare_revisions(app.state(), path.clone(), "main".to_string(), "feature".to_string(), Some(true)).unwrap();
+        assert_eq!(paths(&three_dot), vec!["feature.txt", "shared.txt"]);
+        assert_eq!(three_dot.merge_base_id.as_deref(), Some(base.as_str()));
+        assert_eq!(three_dot.head_id, run_git(repo_path, &["rev-parse", "feature"]));
+        assert_eq!((three_dot.summary.files_changed, three_dot.summary.insertions, three_dot.summary.deletions), (2, 2, 0));
+
+        let diff = get_comparison_file_diff(app.state(), path.clone(), "main".to_string(), "feature".to_string(), "shared.txt".to_string(), Some(true)).unwrap();
+        let added: Vec<&str> = diff.diff_lines.iter().filter(|l| l.line_type == "addition").map(|l| l.content.as_str()).collect();
+        assert_eq!(added, vec!["4"]);
+        assert!(get_comparison_file_diff(app.state(), path.clone(), "main".to_string(), "feature".to_string(), "main.txt".to_string(), Some(true)).is_err());
+
+        let same = compare_revisions(app.state(), path.clone(), base.clone(), "HEAD~1".to_string(), None).unwrap();
+        assert!(same.changes.is_empty());
+        assert!(compare_revisions(app.state(), path, "main".to_string(), "nope".to_string(), None).is_err());
+    }
+
+    #[test]
+    fn test_get_commit_changes() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+
+        // Get the commit ID from the initial commit
+        let commits_result = get_commits_from_path(app.state(), repo_path.to_string_lossy().to_string(), "main".to_string());
+        assert!(commits_result.is_ok());
+        let commits = commits_result.unwrap();
+        assert!(!commits.is_empty());
+
+        let commit_id = &commits[0].id;
+        let result = get_commit_changes(app.state(), repo_path.to_string_lossy().to_string(), commit_id.clone(), None);
+        assert!(result.is_ok());
 
-    fn create_test_git_repo() -> TempDir {
-        let temp_dir = TempDir::new().expect("Failed to create temp directory");
-        let repo_path = temp_dir.path();
+        let changes = result.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "README.md");
+        assert_eq!(changes[0].status, "added");
+    }
 
-        // Initialize git repo
-        Command::new("git")
-            .args(&["init"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to init git repo");
+    #[test]
+    fn test_get_commit_changes_line_stats() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
 
-        // Configure git user for test repo
-        Command::new("git")
-            .args(&["config", "user.name", "Test User"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to configure git user name");
+        commit_file(repo_path, "stats.txt", "one\ntwo\nthree\n", "Add stats file");
+        fs::write(repo_path.join("notes.txt"), "a\nb\n").expect("Failed to write file");
+        run_git(repo_path, &["add", "notes.txt"]);
+        let commit_id = commit_file(repo_path, "stats.txt", "one\n2\nthree\nfour\n", "Edit stats file");
 
-        Command::new("git")
-            .args(&["config", "user.email", "test@example.com"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to configure git user email");
+        let changes = get_commit_changes(app.state(), path.clone(), commit_id.clone(), None).unwrap();
+        let stats = changes.iter().find(|c| c.path == "stats.txt").unwrap();
+        assert_eq!((stats.additions, stats.deletions), (2, 1));
+        let notes = changes.iter().find(|c| c.path == "notes.txt").unwrap();
+        assert_eq!((notes.additions, notes.deletions), (2, 0));
 
-        // Create initial commit
-        fs::write(repo_path.join("README.md"), "# Test Repo").expect("Failed to create README");
-        Command::new("git")
-            .args(&["add", "README.md"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to add README");
+        let summary = get_commit_stats(app.state(), path, commit_id, None).unwrap();
+        assert_eq!(summary.files_changed, 2);
+        assert_eq!(summary.insertions, 4);
+        assert_eq!(summary.deletions, 1);
+    }
 
-        Command::new("git")
-            .args(&["commit", "-m", "Initial commit"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to create initial commit");
+    #[test]
+    fn test_staged_and_stash_line_stats() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
 
-        temp_dir
+        commit_file(repo_path, "a.txt", "1\n2\n", "Add a");
+        fs::write(repo_path.join("a.txt"), "1\n2\n3\n").expect("Failed to write file");
+        fs::write(repo_path.join("b.txt"), "x\n").expect("Failed to write file");
+        run_git(repo_path, &["add", "a.txt", "b.txt"]);
+
+        let staged = get_staged_changes(app.state(), path.clone()).unwrap();
+        let a = staged.iter().find(|c| c.path == "a.txt").unwrap();
+        assert_eq!((a.additions, a.deletions), (1, 0));
+
+        run_git(repo_path, &["stash"]);
+        let changes = get_stash_diff(app.state(), path, 0).unwrap();
+        let a = changes.iter().find(|c| c.path == "a.txt").unwrap();
+        let b = changes.iter().find(|c| c.path == "b.txt").unwrap();
+        assert_eq!((a.additions, a.deletions), (1, 0));
+        assert_eq!((b.additions, b.deletions), (1, 0));
     }
 
     #[test]
-    fn test_get_git_branches_from_path_main_branch() {
+    fn test_get_stash_changes_parts() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
-        let repo_path = temp_repo.path().to_string_lossy().to_string();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
 
-        let result = get_git_branches_from_path(repo_path);
-        assert!(result.is_ok());
+        commit_file(repo_path, "a.txt", "1\n", "Add a");
+        commit_file(repo_path, "b.txt", "1\n", "Add b");
+        fs::write(repo_path.join("a.txt"), "1\n2\n").expect("Failed to write file");
+        run_git(repo_path, &["add", "a.txt"]);
+        fs::write(repo_path.join("b.txt"), "changed\n").expect("Failed to write file");
+        fs::write(repo_path.join("c.txt"), "new\n").expect("Failed to write file");
+        run_git(repo_path, &["stash", "push", "--include-untracked"]);
 
-        let branches = result.unwrap();
-        assert_eq!(branches.len(), 1);
-        assert_eq!(branches[0].name, "main");
-        assert!(branches[0].is_current);
+        let changes = get_stash_changes(app.state(), path.clone(), 0).unwrap();
+        let paths = |list: &Vec<FileChange>| list.iter().map(|c| c.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(&changes.staged), vec!["a.txt"]);
+        assert_eq!(paths(&changes.unstaged), vec!["b.txt"]);
+        assert_eq!(paths(&changes.untracked), vec!["c.txt"]);
+        assert_eq!(changes.untracked[0].status, "added");
+
+        let diff = get_stash_part_file_diff(app.state(), path.clone(), 0, "untracked".to_string(), "c.txt".to_string()).unwrap();
+        assert!(diff.diff_lines.iter().any(|l| l.line_type == "addition" && l.content == "new"));
+        assert!(get_stash_part_file_diff(app.state(), path.clone(), 0, "staged".to_string(), "b.txt".to_string()).is_err());
+        assert!(get_stash_part_file_diff(app.state(), path, 0, "other".to_string(), "a.txt".to_string()).is_err());
     }
 
     #[test]
-    fn test_get_git_branches_from_path_multiple_branches() {
+    fn test_stash_push_pop_and_drop() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
 
-        // Create a new branch
-        Command::new("git")
-            .args(&["checkout", "-b", "feature-branch"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to create feature branch");
+        commit_file(repo_path, "a.txt", "1\n", "Add a");
+        fs::write(repo_path.join("a.txt"), "2\n").expect("Failed to write file");
+        fs::write(repo_path.join("new.txt"), "new\n").expect("Failed to write file");
 
-        // Switch back to main
-        Command::new("git")
-            .args(&["checkout", "main"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to switch to main");
+        let stash = stash_push(app.state(), path.clone(), Some("wip".to_string()), Some(true), None).unwrap();
+        assert_eq!(stash.index, 0);
+        assert!(stash.message.contains("wip"));
+        assert!(!repo_path.join("new.txt").exists());
+        assert!(stash_push(app.state(), path.clone(), None, None, None).is_err());
 
-        let result = get_git_branches_from_path(repo_path.to_string_lossy().to_string());
-        assert!(result.is_ok());
+        let result = stash_pop(app.state(), path.clone(), 0, None).unwrap();
+        assert!(result.conflicts.is_empty());
+        assert!(result.dropped);
+        assert_eq!(fs::read_to_string(repo_path.join("a.txt")).unwrap(), "2\n");
+        assert!(repo_path.join("new.txt").exists());
+        assert!(get_stashes(app.state(), path.clone()).unwrap().is_empty());
 
-        let branches = result.unwrap();
-        assert_eq!(branches.len(), 2);
+        stash_push(app.state(), path.clone(), None, Some(true), None).unwrap();
+        stash_drop(app.state(), path.clone(), 0).unwrap();
+        assert!(get_stashes(app.state(), path.clone()).unwrap().is_empty());
+        assert!(stash_drop(app.state(), path, 0).is_err());
+    }
 
-        // Find main and feature branches
-        let main_branch = branches.iter().find(|b| b.name == "main").unwrap();
-        let feature_branch = branches.iter().find(|b| b.name == "feature-branch").unwrap();
+    #[test]
+    fn test_stash_pop_conflict_keeps_stash() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
 
-        assert!(main_branch.is_current);
-        assert!(!feature_branch.is_current);
+        commit_file(repo_path, "a.txt", "1\n", "Add a");
+        fs::write(repo_path.join("a.txt"), "stashed\n").expect("Failed to write file");
+        stash_push(app.state(), path.clone(), None, None, None).unwrap();
+        commit_file(repo_path, "a.txt", "committed\n", "Change a");
+
+        let result = stash_pop(app.state(), path.clone(), 0, None).unwrap();
+        assert_eq!(result.conflicts, vec!["a.txt".to_string()]);
+        assert!(!result.dropped);
+        assert_eq!(get_stashes(app.state(), path).unwrap().len(), 1);
     }
 
     #[test]
-    fn test_get_git_branches_from_path_invalid_path() {
-        let result = get_git_branches_from_path("/invalid/path".to_string());
-        assert!(result.is_err());
+    fn test_stash_branch() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let base = commit_file(repo_path, "a.txt", "1\n", "Add a");
+        fs::write(repo_path.join("a.txt"), "2\n").expect("Failed to write file");
+        run_git(repo_path, &["add", "a.txt"]);
+        stash_push(app.state(), path.clone(), None, None, None).unwrap();
+        commit_file(repo_path, "a.txt", "3\n", "Change a");
+
+        let result = stash_branch(app.state(), path.clone(), 0, "from-stash".to_string()).unwrap();
+        assert!(result.dropped);
+        assert_eq!(run_git(repo_path, &["rev-parse", "--abbrev-ref", "HEAD"]), "from-stash");
+        assert_eq!(run_git(repo_path, &["rev-parse", "HEAD"]), base);
+        assert_eq!(run_git(repo_path, &["diff", "--cached", "--name-only"]), "a.txt");
+        assert!(stash_branch(app.state(), path.clone(), 0, "bad name".to_string()).is_err());
+
+        // When the stash can't be applied, the new branch is removed and HEAD restored
+        run_git(repo_path, &["checkout", "-q", "-f", "main"]);
+        fs::write(repo_path.join("u.txt"), "stashed\n").expect("Failed to write file");
+        stash_push(app.state(), path.clone(), None, Some(true), None).unwrap();
+        fs::write(repo_path.join("u.txt"), "in the way\n").expect("Failed to write file");
+        assert!(stash_branch(app.state(), path.clone(), 0, "blocked".to_string()).is_err());
+        assert_eq!(run_git(repo_path, &["branch", "--list", "blocked"]), "");
+        assert_eq!(run_git(repo_path, &["rev-parse", "--abbrev-ref", "HEAD"]), "main");
+        assert_eq!(get_stashes(app.state(), path).unwrap().len(), 1);
     }
 
     #[test]
-    fn test_get_git_branches_from_path_non_git_directory() {
-        let temp_dir = TempDir::new().expect("Failed to create temp directory");
-        let non_git_path = temp_dir.path().to_string_lossy().to_string();
+    fn test_get_file_blame_at_older_commit() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
 
-        let result = get_git_branches_from_path(non_git_path);
-        assert!(result.is_err());
+        let first = commit_file(repo_path, "blame.txt", "alpha\nbeta\n", "Add blame file");
+        let second = commit_file(repo_path, "blame.txt", "inserted\nalpha\nbeta\n", "Insert a line");
+
+        // At the first commit both lines belong to it, not to HEAD
+        let blame = get_file_blame(app.state(), path.clone(), first.clone(), "blame.txt".to_string(), None).unwrap();
+        assert_eq!(blame.blame_lines.len(), 2);
+        assert!(blame.blame_lines.iter().all(|l| l.commit_id == first));
+        assert_eq!(blame.blame_lines[0].content, "alpha");
+
+        let blame = get_file_blame(app.state(), path, second.clone(), "blame.txt".to_string(), None).unwrap();
+        assert_eq!(blame.blame_lines[0].commit_id, second);
+        assert_eq!(blame.blame_lines[1].commit_id, first);
     }
 
     #[test]
-    fn test_get_commits_from_path() {
+    fn test_get_file_blame_working_tree() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
-        let repo_path = temp_repo.path().to_string_lossy().to_string();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
 
-        let result = get_commits_from_path(repo_path, "main".to_string());
-        assert!(result.is_ok());
+        let head = commit_file(repo_path, "blame.txt", "alpha\nbeta\n", "Add blame file");
+        fs::write(repo_path.join("blame.txt"), "alpha\nchanged\n").expect("Failed to write file");
 
-        let commits = result.unwrap();
-        assert_eq!(commits.len(), 1);
-        assert_eq!(commits[0].message, "Initial commit");
-        assert!(commits[0].author.contains("Test User"));
-        assert!(commits[0].short_id.len() == 8);
+        let blame = get_file_blame(app.state(), path.clone(), head.clone(), "blame.txt".to_string(), Some(true)).unwrap();
+        assert_eq!(blame.blame_lines.len(), 2);
+        assert_eq!(blame.blame_lines[0].commit_id, head);
+        assert_eq!(blame.blame_lines[1].content, "changed");
+        assert_eq!(blame.blame_lines[1].author, "Not Committed Yet");
+
+        // Files that are not in HEAD yet are entirely uncommitted
+        fs::write(repo_path.join("new.txt"), "one\ntwo\n").expect("Failed to write file");
+        let blame = get_file_blame(app.state(), path.clone(), head.clone(), "new.txt".to_string(), Some(true)).unwrap();
+        assert_eq!(blame.blame_lines.len(), 2);
+        assert!(blame.blame_lines.iter().all(|l| l.author == "Not Committed Yet" && l.commit_id == git2::Oid::zero().to_string()));
+        assert_eq!(blame.blame_lines[1].content, "two");
+
+        run_git(repo_path, &["add", "new.txt"]);
+        let blame = get_file_blame(app.state(), path, head, "new.txt".to_string(), Some(true)).unwrap();
+        assert_eq!(blame.blame_lines[0].author, "Not Committed Yet");
     }
 
     #[test]
-    fn test_get_commits_from_path_invalid_branch() {
+    fn test_get_file_blame_range_beyond_line_limit() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
-        let repo_path = temp_repo.path().to_string_lossy().to_string();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let content: String = (1..=3500).map(|i| format!("line {}\n", i)).collect();
+        let commit_id = commit_file(repo_path, "big.txt", &content, "Add big file");
+
+        assert!(get_file_blame(app.state(), path.clone(), commit_id.clone(), "big.txt".to_string(), None).is_err());
+
+        let blame = get_file_blame_range(app.state(), path.clone(), commit_id.clone(), "big.txt".to_string(), 3201, 3210).unwrap();
+        assert_eq!(blame.blame_lines.len(), 10);
+        assert_eq!(blame.blame_lines[0].line_number, 3201);
+        assert_eq!(blame.blame_lines[0].content, "line 3201");
+        assert!(blame.blame_lines.iter().all(|l| l.commit_id == commit_id));
+
+        // Ranges past the end are clamped to the file
+        let blame = get_file_blame_range(app.state(), path, commit_id, "big.txt".to_string(), 3499, 4000).unwrap();
+        assert_eq!(blame.blame_lines.len(), 2);
+    }
+
+    #[test]
+    fn test_blame_in_chunks() {
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let first = commit_file(repo_path, "chunks.txt", "a\nb\nc\n", "Add chunks file");
+        let second = commit_file(repo_path, "chunks.txt", "a\nb\nc\nd\ne\n", "Extend chunks file");
+
+        let repo = git2::Repository::open(&path).unwrap();
+        let streams = BlameStreams::default();
+        let (stream_id, cancelled) = streams.start();
+        let mut chunks = Vec::new();
+        blame_in_chunks(&repo, &cancelled, stream_id, &second, "chunks.txt", "a\nb\nc\nd\ne\n", 2, |chunk| chunks.push(chunk));
+
+        assert_eq!(chunks.iter().map(|c| c.start_line).collect::<Vec<_>>(), vec![1, 3, 5]);
+        assert!(chunks[2].done && !chunks[0].done);
+        let lines: Vec<_> = chunks.iter().flat_map(|c| c.blame_lines.iter()).collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[2].commit_id, first);
+        assert_eq!(lines[4].commit_id, second);
+
+        // Cancelling one stream leaves the others running
+        let (other_id, other_cancelled) = streams.start();
+        streams.cancel(stream_id);
+        assert!(!other_cancelled.load(Ordering::SeqCst));
+        let mut cancelled_chunks = Vec::new();
+        blame_in_chunks(&repo, &cancelled, stream_id, &second, "chunks.txt", "a\nb\nc\nd\ne\n", 2, |chunk| cancelled_chunks.push(chunk));
+        assert!(cancelled_chunks.is_empty());
+
+        let mut errors = Vec::new();
+        blame_in_chunks(&repo, &other_cancelled, other_id, &second, "missing.txt", "a\n", 2, |chunk| errors.push(chunk));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].done && errors[0].error.is_some());
+    }
+
+    #[test]
+    fn test_blame_line_at_parent() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let first = commit_file(repo_path, "walk.txt", "a\nb\nc\n", "Add walk file");
+        let reformat = commit_file(repo_path, "walk.txt", "a\nB\nc\n", "Reformat b");
+        let head = commit_file(repo_path, "walk.txt", "z\na\nB\nc\n", "Prepend z");
 
-        let result = get_commits_from_path(repo_path, "nonexistent-branch".to_string());
+        // Line 3 ("B") at HEAD was last touched by the reformat commit
+        let previous = blame_line_at_parent(app.state(), path.clone(), head, "walk.txt".to_string(), 3).unwrap();
+        assert_eq!(previous.skipped_commit_id, reformat);
+        assert_eq!(previous.revision, first);
+        assert_eq!(previous.path, "walk.txt");
+        assert_eq!(previous.blame.line_number, 2);
+        assert_eq!(previous.blame.content, "b");
+        assert_eq!(previous.blame.commit_id, first);
+
+        // Walking further back runs into the root commit
+        let result = blame_line_at_parent(app.state(), path, previous.revision, previous.path, previous.blame.line_number);
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_get_commit_changes() {
+    fn test_get_file_blame_original_path_and_line() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
 
-        // Get the commit ID from the initial commit
-        let commits_result = get_commits_from_path(repo_path.to_string_lossy().to_string(), "main".to_string());
-        assert!(commits_result.is_ok());
-        let commits = commits_result.unwrap();
-        assert!(!commits.is_empty());
+        let body: String = (1..=8).map(|i| format!("fn moved_{}() {{}}\n", i)).collect();
+        let origin = commit_file(repo_path, "old.rs", &body, "Add old module");
 
-        let commit_id = &commits[0].id;
-        let result = get_commit_changes(repo_path.to_string_lossy().to_string(), commit_id.clone());
-        assert!(result.is_ok());
+        // Move the code into another file with a header line in front
+        fs::remove_file(repo_path.join("old.rs")).expect("Failed to remove file");
+        run_git(repo_path, &["add", "-A"]);
+        let head = commit_file(repo_path, "new.rs", &format!("// header\n{}", body), "Move code to new module");
 
-        let changes = result.unwrap();
-        assert_eq!(changes.len(), 1);
-        assert_eq!(changes[0].path, "README.md");
-        assert_eq!(changes[0].status, "added");
+        let blame = get_file_blame(app.state(), path, head.clone(), "new.rs".to_string(), None).unwrap();
+        let header = &blame.blame_lines[0];
+        assert_eq!(header.commit_id, head);
+        assert_eq!(header.original_path, "new.rs");
+        assert_eq!(header.committer, "Test User");
+
+        let moved = &blame.blame_lines[3];
+        assert_eq!(moved.commit_id, origin);
+        assert_eq!(moved.original_path, "old.rs");
+        assert_eq!(moved.line_number, 4);
+        assert_eq!(moved.original_line_number, 3);
+        assert!(!moved.is_boundary);
     }
 
     #[test]
     fn test_get_file_diff() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path();
 
         // Get the commit ID from the initial commit
-        let commits_result = get_commits_from_path(repo_path.to_string_lossy().to_string(), "main".to_string());
+        let commits_result = get_commits_from_path(app.state(), repo_path.to_string_lossy().to_string(), "main".to_string());
         assert!(commits_result.is_ok());
         let commits = commits_result.unwrap();
         assert!(!commits.is_empty());
 
         let commit_id = &commits[0].id;
-        let result = get_file_diff(
+        let result = get_file_diff(app.state(), 
             repo_path.to_string_lossy().to_string(), 
             commit_id.clone(), 
-            "README.md".to_string()
+            "README.md".to_string(),
+            None
         );
         assert!(result.is_ok());
 
@@ -1605,12 +6265,332 @@ mod tests {
         assert!(!diff.diff_lines.is_empty());
     }
 
+    #[test]
+    fn test_get_working_tree_changes() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        commit_file(repo_path, "tracked.txt", "one\ntwo\n", "Add tracked file");
+        fs::write(repo_path.join("tracked.txt"), "one\n2\nthree\n").expect("Failed to write file");
+        fs::create_dir_all(repo_path.join("notes")).expect("Failed to create directory");
+        fs::write(repo_path.join("notes/todo.txt"), "buy milk\n").expect("Failed to write file");
+        fs::remove_file(repo_path.join("README.md")).expect("Failed to remove file");
+
+        let changes = get_working_tree_changes(app.state(), path.clone()).unwrap();
+        let tracked = changes.iter().find(|c| c.path == "tracked.txt").unwrap();
+        assert_eq!(tracked.status, "modified");
+        assert_eq!((tracked.additions, tracked.deletions), (2, 1));
+        let untracked = changes.iter().find(|c| c.path == "notes/todo.txt").unwrap();
+        assert_eq!(untracked.status, "untracked");
+        assert_eq!(untracked.additions, 1);
+        assert_eq!(changes.iter().find(|c| c.path == "README.md").unwrap().status, "deleted");
+
+        // Staged files no longer show up as working tree changes
+        run_git(repo_path, &["add", "tracked.txt"]);
+        let changes = get_working_tree_changes(app.state(), path.clone()).unwrap();
+        assert!(changes.iter().all(|c| c.path != "tracked.txt"));
+
+        let diff = get_working_tree_file_diff(app.state(), path, "notes/todo.txt".to_string()).unwrap();
+        assert_eq!(diff.status, "untracked");
+        assert_eq!(diff.diff_lines.len(), 1);
+        assert_eq!(diff.diff_lines[0].line_type, "addition");
+        assert_eq!(diff.diff_lines[0].content, "buy milk");
+    }
+
+    #[test]
+    fn test_get_working_tree_changes_conflict() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        commit_file(repo_path, "clash.txt", "base\n", "Add clash file");
+        run_git(repo_path, &["checkout", "-b", "other"]);
+        commit_file(repo_path, "clash.txt", "theirs\n", "Change on other");
+        run_git(repo_path, &["checkout", "main"]);
+        commit_file(repo_path, "clash.txt", "ours\n", "Change on main");
+        run_git(repo_path, &["merge", "other"]);
+
+        let changes = get_working_tree_changes(app.state(), path.clone()).unwrap();
+        let clash = changes.iter().find(|c| c.path == "clash.txt").unwrap();
+        assert_eq!(clash.status, "conflicted");
+
+        let diff = get_working_tree_file_diff(app.state(), path, "clash.txt".to_string()).unwrap();
+        assert_eq!(diff.status, "conflicted");
+        assert!(diff.diff_lines.iter().any(|l| l.line_type == "addition" && l.content.starts_with("<<<<<<<")));
+    }
+
+    #[test]
+    fn test_stage_and_unstage_file() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        fs::write(repo_path.join("README.md"), "# Changed").expect("Failed to write file");
+        fs::write(repo_path.join("new.txt"), "new\n").expect("Failed to write file");
+
+        stage_file(app.state(), path.clone(), "README.md".to_string()).unwrap();
+        stage_file(app.state(), path.clone(), "new.txt".to_string()).unwrap();
+        let staged = get_staged_changes(app.state(), path.clone()).unwrap();
+        assert_eq!(staged.len(), 2);
+        assert_eq!(staged.iter().find(|c| c.path == "new.txt").unwrap().status, "added");
+
+        unstage_file(app.state(), path.clone(), "README.md".to_string()).unwrap();
+        let staged = get_staged_changes(app.state(), path.clone()).unwrap();
+        assert_eq!(staged.len(), 1);
+        assert_eq!(staged[0].path, "new.txt");
+
+        // Staging a deleted file stages the deletion
+        fs::remove_file(repo_path.join("README.md")).expect("Failed to remove file");
+        stage_file(app.state(), path.clone(), "README.md".to_string()).unwrap();
+        let staged = get_staged_changes(app.state(), path).unwrap();
+        assert_eq!(staged.iter().find(|c| c.path == "README.md").unwrap().status, "deleted");
+    }
+
+    #[test]
+    fn test_stage_and_unstage_hunk() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let original: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        commit_file(repo_path, "hunks.txt", &original, "Add hunks file");
+        let changed = original.replace("line 2\n", "line two\n").replace("line 19\n", "line nineteen\n");
+        fs::write(repo_path.join("hunks.txt"), &changed).expect("Failed to write file");
+
+        let diff = get_working_tree_file_diff(app.state(), path.clone(), "hunks.txt".to_string()).unwrap();
+        let last_hunk = diff.diff_lines.iter().map(|l| l.hunk_index).max().unwrap();
+        assert_eq!(last_hunk, 1);
+
+        stage_hunk(app.state(), path.clone(), "hunks.txt".to_string(), 0).unwrap();
+        assert_eq!(run_git(repo_path, &["show", ":hunks.txt"]), original.replace("line 2\n", "line two\n").trim_end());
+        let unstaged = get_working_tree_file_diff(app.state(), path.clone(), "hunks.txt".to_string()).unwrap();
+        assert!(unstaged.diff_lines.iter().any(|l| l.content == "line nineteen"));
+        assert!(unstaged.diff_lines.iter().all(|l| l.content != "line two"));
+
+        unstage_hunk(app.state(), path.clone(), "hunks.txt".to_string(), 0).unwrap();
+        assert_eq!(run_git(repo_path, &["show", ":hunks.txt"]), original.trim_end());
+        assert!(get_staged_changes(app.state(), path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stage_and_unstage_lines() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        commit_file(repo_path, "lines.txt", "a\nb\nc\n", "Add lines file");
+        fs::write(repo_path.join("lines.txt"), "a\nB\nc\nd\n").expect("Failed to write file");
+
+        // Stage only the appended "d", not the b -> B edit
+        let selection = vec![LineSelection { old_line_number: None, new_line_number: Some(4) }];
+        stage_lines(app.state(), path.clone(), "lines.txt".to_string(), selection).unwrap();
+        assert_eq!(run_git(repo_path, &["show", ":lines.txt"]), "a\nb\nc\nd");
+
+        // Stage the deletion of "b" without its replacement
+        let selection = vec![LineSelection { old_line_number: Some(2), new_line_number: None }];
+        stage_lines(app.state(), path.clone(), "lines.txt".to_string(), selection).unwrap();
+        assert_eq!(run_git(repo_path, &["show", ":lines.txt"]), "a\nc\nd");
+
+        // Unstage the added "d" again
+        let selection = vec![LineSelection { old_line_number: None, new_line_number: Some(3) }];
+        unstage_lines(app.state(), path.clone(), "lines.txt".to_string(), selection).unwrap();
+        assert_eq!(run_git(repo_path, &["show", ":lines.txt"]), "a\nc");
+        assert_eq!(fs::read_to_string(repo_path.join("lines.txt")).unwrap(), "a\nB\nc\nd\n");
+
+        // Staging every line of a deleted file removes it from the index
+        commit_file(repo_path, "gone.txt", "x\ny\n", "Add gone file");
+        fs::remove_file(repo_path.join("gone.txt")).expect("Failed to delete file");
+        let selection = vec![
+            LineSelection { old_line_number: Some(1), new_line_number: None },
+            LineSelection { old_line_number: Some(2), new_line_number: None },
+        ];
+        stage_lines(app.state(), path.clone(), "gone.txt".to_string(), selection).unwrap();
+        assert_eq!(run_git(repo_path, &["diff", "--cached", "--name-status", "--", "gone.txt"]), "D\tgone.txt");
+    }
+
+    #[test]
+    fn test_stage_and_discard_working_tree_rename() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let content = "one\ntwo\nthree\nfour\nfive\n";
+        commit_file(repo_path, "old.txt", content, "Add old file");
+        fs::rename(repo_path.join("old.txt"), repo_path.join("new.txt")).expect("Failed to rename file");
+
+        let changes = get_working_tree_changes(app.state(), path.clone()).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].path.as_str(), changes[0].old_path.as_deref()), ("new.txt", Some("old.txt")));
+
+        // Staging the new path stages the whole rename
+        stage_file(app.state(), path.clone(), "new.txt".to_string()).unwrap();
+        assert_eq!(run_git(repo_path, &["status", "--porcelain"]), "R  old.txt -> new.txt");
+        run_git(repo_path, &["reset", "-q"]);
+
+        // Discarding it brings the old path back
+        let files = vec!["new.txt".to_string()];
+        let plan = prepare_discard(app.state(), path.clone(), files.clone()).unwrap();
+        discard_changes(app.state(), path, files, plan.token).unwrap();
+        assert_eq!(fs::read_to_string(repo_path.join("old.txt")).unwrap(), content);
+        assert!(!repo_path.join("new.txt").exists());
+        assert_eq!(run_git(repo_path, &["status", "--porcelain"]), "");
+    }
+
+    #[test]
+    fn test_discard_changes_requires_matching_token() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        fs::write(repo_path.join("README.md"), "# Changed").expect("Failed to write file");
+        fs::write(repo_path.join("scratch.txt"), "scratch\n").expect("Failed to write file");
+        let files = vec!["README.md".to_string(), "scratch.txt".to_string()];
+
+        let plan = prepare_discard(app.state(), path.clone(), files.clone()).unwrap();
+        assert_eq!(plan.files.len(), 2);
+
+        // Editing a file after confirmation invalidates the token
+        fs::write(repo_path.join("README.md"), "# Changed again").expect("Failed to write file");
+        assert!(discard_changes(app.state(), path.clone(), files.clone(), plan.token).is_err());
+        assert_eq!(fs::read_to_string(repo_path.join("README.md")).unwrap(), "# Changed again");
+
+        let plan = prepare_discard(app.state(), path.clone(), files.clone()).unwrap();
+        discard_changes(app.state(), path.clone(), files, plan.token).unwrap();
+        assert_eq!(fs::read_to_string(repo_path.join("README.md")).unwrap(), "# Test Repo");
+        assert!(!repo_path.join("scratch.txt").exists());
+
+        assert!(prepare_discard(app.state(), path, vec!["README.md".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_discard_changes_refuses_conflicted_file() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        commit_file(repo_path, "clash.txt", "base\n", "Add clash file");
+        run_git(repo_path, &["checkout", "-b", "other"]);
+        commit_file(repo_path, "clash.txt", "theirs\n", "Change on other");
+        run_git(repo_path, &["checkout", "main"]);
+        commit_file(repo_path, "clash.txt", "ours\n", "Change on main");
+        run_git(repo_path, &["merge", "other"]);
+        let files = vec!["clash.txt".to_string()];
+
+        let error = prepare_discard(app.state(), path.clone(), files.clone()).unwrap_err();
+        assert!(error.contains("unmerged"));
+
+        // Even with a valid token the file must not be deleted
+        let token = {
+            let handle = app.state::<RepoSessions>().open(&path).unwrap();
+            let repo = handle.lock();
+            discard_token(&repo, &files).unwrap()
+        };
+        assert!(discard_changes(app.state(), path, files, token).is_err());
+        assert!(repo_path.join("clash.txt").exists());
+        assert_eq!(run_git(repo_path, &["status", "--porcelain"]), "UU clash.txt");
+    }
+
+    #[test]
+    fn test_create_commit() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        assert!(create_commit(app.state(), path.clone(), "Nothing here".to_string(), None, None).is_err());
+
+        fs::write(repo_path.join("feature.txt"), "feature\n").expect("Failed to write file");
+        stage_file(app.state(), path.clone(), "feature.txt".to_string()).unwrap();
+
+        let commit = create_commit(app.state(), path.clone(), "Add feature\n\nWith details  \n".to_string(), None, None).unwrap();
+        assert_eq!(commit.message, "Add feature");
+        assert_eq!(commit.author, "Test User");
+        assert_eq!(run_git(repo_path, &["rev-parse", "HEAD"]), commit.id);
+        assert_eq!(run_git(repo_path, &["log", "-1", "--format=%B"]), "Add feature\n\nWith details");
+        assert!(get_staged_changes(app.state(), path.clone()).unwrap().is_empty());
+
+        let amended = create_commit(app.state(), path.clone(), "Add feature, amended".to_string(), Some(true), None).unwrap();
+        assert_ne!(amended.id, commit.id);
+        assert_eq!(run_git(repo_path, &["rev-list", "--count", "HEAD"]), "2");
+        assert_eq!(get_commits_from_path(app.state(), path, "main".to_string()).unwrap()[0].message, "Add feature, amended");
+    }
+
+    #[test]
+    fn test_create_commit_keeps_cherry_pick_state() {
+        let app = test_app();
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        commit_file(repo_path, "pick.txt", "base\n", "Add pick file");
+        run_git(repo_path, &["checkout", "-b", "other"]);
+        let picked = commit_file(repo_path, "pick.txt", "theirs\n", "Change on other");
+        run_git(repo_path, &["checkout", "main"]);
+        commit_file(repo_path, "pick.txt", "ours\n", "Change on main");
+        run_git(repo_path, &["cherry-pick", &picked]);
+
+        fs::write(repo_path.join("pick.txt"), "resolved\n").expect("Failed to write file");
+        stage_file(app.state(), path.clone(), "pick.txt".to_string()).unwrap();
+
+        let error = create_commit(app.state(), path, "Resolved".to_string(), None, None).unwrap_err();
+        assert!(error.contains("cherry-pick"));
+        assert!(repo_path.join(".git/CHERRY_PICK_HEAD").exists());
+        assert_eq!(run_git(repo_path, &["log", "-1", "--format=%s"]), "Change on main");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_commit_runs_hooks() {
+        let app = test_app();
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_repo = create_test_git_repo();
+        let repo_path = temp_repo.path();
+        let path = repo_path.to_string_lossy().to_string();
+
+        let hooks_dir = repo_path.join(".git/hooks");
+        fs::create_dir_all(&hooks_dir).expect("Failed to create hooks directory");
+        let write_hook = |name: &str, script: &str| {
+            let hook = hooks_dir.join(name);
+            fs::write(&hook, script).expect("Failed to write hook");
+            fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).expect("Failed to make hook executable");
+        };
+
+        fs::write(repo_path.join("hooked.txt"), "hooked\n").expect("Failed to write file");
+        stage_file(app.state(), path.clone(), "hooked.txt".to_string()).unwrap();
+
+        write_hook("pre-commit", "#!/bin/sh\necho 'lint failed' >&2\nexit 1\n");
+        let result = create_commit(app.state(), path.clone(), "Blocked".to_string(), None, None);
+        assert!(result.unwrap_err().contains("lint failed"));
+
+        // Skipping verification ignores both hooks
+        write_hook("commit-msg", "#!/bin/sh\necho 'Signed-off-by: Hook' >> \"$1\"\n");
+        create_commit(app.state(), path.clone(), "Skip hooks".to_string(), Some(true), Some(true)).unwrap();
+        assert_eq!(run_git(repo_path, &["log", "-1", "--format=%B"]), "Skip hooks");
+
+        write_hook("pre-commit", "#!/bin/sh\nexit 0\n");
+        fs::write(repo_path.join("hooked.txt"), "hooked again\n").expect("Failed to write file");
+        stage_file(app.state(), path.clone(), "hooked.txt".to_string()).unwrap();
+        create_commit(app.state(), path, "With hooks".to_string(), None, None).unwrap();
+        assert_eq!(run_git(repo_path, &["log", "-1", "--format=%B"]), "With hooks\nSigned-off-by: Hook");
+    }
+
     #[test]
     fn test_global_search_empty_query() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path().to_string_lossy().to_string();
 
-        let result = global_search(repo_path, "".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path, "".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         assert_eq!(results.len(), 0);
@@ -1618,10 +6598,11 @@ mod tests {
 
     #[test]
     fn test_global_search_commit_message() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path().to_string_lossy().to_string();
 
-        let result = global_search(repo_path, "Initial".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path, "Initial".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         
@@ -1638,10 +6619,11 @@ mod tests {
 
     #[test]
     fn test_global_search_file_name() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path().to_string_lossy().to_string();
 
-        let result = global_search(repo_path, "README".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path, "README".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         
@@ -1658,6 +6640,7 @@ mod tests {
 
     #[test]
     fn test_global_search_file_content() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path();
 
@@ -1674,7 +6657,7 @@ mod tests {
             .output()
             .expect("Failed to commit test file");
 
-        let result = global_search(repo_path.to_string_lossy().to_string(), "specific".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path.to_string_lossy().to_string(), "specific".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         
@@ -1694,11 +6677,12 @@ mod tests {
 
     #[test]
     fn test_global_search_case_insensitive() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path().to_string_lossy().to_string();
 
         // Test case insensitive search for commit message
-        let result = global_search(repo_path.clone(), "INITIAL".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path.clone(), "INITIAL".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         
@@ -1709,10 +6693,11 @@ mod tests {
 
     #[test]
     fn test_global_search_no_results() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path().to_string_lossy().to_string();
 
-        let result = global_search(repo_path, "nonexistentstring123".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path, "nonexistentstring123".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         assert_eq!(results.len(), 0);
@@ -1720,17 +6705,19 @@ mod tests {
 
     #[test]
     fn test_global_search_invalid_repository() {
-        let result = global_search("/invalid/path".to_string(), "test".to_string(), Some("main".to_string()), None);
+        let app = test_app();
+        let result = global_search(app.state(), "/invalid/path".to_string(), "test".to_string(), Some("main".to_string()), None);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_global_search_invalid_branch() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path().to_string_lossy().to_string();
 
         // Should still work by falling back to HEAD
-        let result = global_search(repo_path, "Initial".to_string(), Some("nonexistent-branch".to_string()), None);
+        let result = global_search(app.state(), repo_path, "Initial".to_string(), Some("nonexistent-branch".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         assert!(!results.is_empty());
@@ -1738,6 +6725,7 @@ mod tests {
 
     #[test]
     fn test_global_search_multiple_commits() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path();
 
@@ -1760,7 +6748,7 @@ mod tests {
                 .expect("Failed to commit file");
         }
 
-        let result = global_search(repo_path.to_string_lossy().to_string(), "searchable".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path.to_string_lossy().to_string(), "searchable".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         
@@ -1776,6 +6764,7 @@ mod tests {
 
     #[test]
     fn test_global_search_result_limit() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path();
 
@@ -1797,7 +6786,7 @@ mod tests {
                 .expect("Failed to commit file");
         }
 
-        let result = global_search(repo_path.to_string_lossy().to_string(), "uniquelimitsearch".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path.to_string_lossy().to_string(), "uniquelimitsearch".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         
@@ -1815,6 +6804,7 @@ mod tests {
 
     #[test]
     fn test_global_search_filters_merge_commits() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path();
 
@@ -1851,7 +6841,7 @@ mod tests {
             .output()
             .expect("Failed to merge feature branch");
 
-        let result = global_search(repo_path.to_string_lossy().to_string(), "mergetest".to_string(), Some("main".to_string()), None);
+        let result = global_search(app.state(), repo_path.to_string_lossy().to_string(), "mergetest".to_string(), Some("main".to_string()), None);
         assert!(result.is_ok());
         let results = result.unwrap();
         
@@ -1875,6 +6865,7 @@ mod tests {
 
     #[test]
     fn test_global_search_custom_limit() {
+        let app = test_app();
         let temp_repo = create_test_git_repo();
         let repo_path = temp_repo.path();
 
@@ -1895,7 +6886,7 @@ mod tests {
         }
 
         // Test with limit of 3 commits
-        let result = global_search(repo_path.to_string_lossy().to_string(), "customlimit".to_string(), Some("main".to_string()), Some(3));
+        let result = global_search(app.state(), repo_path.to_string_lossy().to_string(), "customlimit".to_string(), Some("main".to_string()), Some(3));
         assert!(result.is_ok());
         let results = result.unwrap();
         
@@ -1903,7 +6894,7 @@ mod tests {
         assert!(!results.is_empty());
         
         // Test with unlimited (None should use default 100)
-        let result_unlimited = global_search(repo_path.to_string_lossy().to_string(), "customlimit".to_string(), Some("main".to_string()), None);
+        let result_unlimited = global_search(app.state(), repo_path.to_string_lossy().to_string(), "customlimit".to_string(), Some("main".to_string()), None);
         assert!(result_unlimited.is_ok());
         let results_unlimited = result_unlimited.unwrap();
         